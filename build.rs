@@ -0,0 +1,66 @@
+use std::{env, fs, path::Path, process::Command};
+
+fn main() {
+    println!("cargo:rustc-env=EC2_BY_NAME_GIT_SHA={}", git_sha().unwrap_or_else(|| "unknown".to_string()));
+    println!("cargo:rustc-env=EC2_BY_NAME_RUSTC_VERSION={}", rustc_version().unwrap_or_else(|| "unknown".to_string()));
+    println!(
+        "cargo:rustc-env=EC2_BY_NAME_AWS_SDK_EC2_VERSION={}",
+        locked_version("aws-sdk-ec2").unwrap_or_else(|| "unknown".to_string())
+    );
+
+    for path in git_rerun_paths() {
+        println!("cargo:rerun-if-changed={path}");
+    }
+    println!("cargo:rerun-if-changed=Cargo.lock");
+}
+
+/// The git files that change when HEAD moves: HEAD itself (switching branches), the branch's ref file (committing),
+/// and packed-refs (where the branch's ref lives after `git gc`). Files that don't exist are omitted, since Cargo would
+/// otherwise rerun the build script every time.
+fn git_rerun_paths() -> Vec<String> {
+    let mut paths = vec![".git/HEAD".to_string()];
+    if let Some(branch_ref) = fs::read_to_string(".git/HEAD")
+        .ok()
+        .and_then(|head| head.trim().strip_prefix("ref: ").map(|branch_ref| format!(".git/{branch_ref}")))
+    {
+        paths.push(branch_ref);
+    }
+    paths.push(".git/packed-refs".to_string());
+    paths.retain(|path| Path::new(path).exists());
+    paths
+}
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    Some(String::from_utf8(output.stdout).ok()?.trim().to_string())
+}
+
+fn git_sha() -> Option<String> {
+    command_output("git", &["rev-parse", "HEAD"])
+}
+
+fn rustc_version() -> Option<String> {
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    command_output(&rustc, &["--version"])
+}
+
+/// Read the version of a package from Cargo.lock.
+fn locked_version(package: &str) -> Option<String> {
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").ok()?;
+    let lock = fs::read_to_string(Path::new(&manifest_dir).join("Cargo.lock")).ok()?;
+    let name_line = format!("name = \"{package}\"");
+    let mut lines = lock.lines();
+
+    while let Some(line) = lines.next() {
+        if line == name_line {
+            let version_line = lines.next()?;
+            return Some(version_line.strip_prefix("version = ")?.trim_matches('"').to_string());
+        }
+    }
+
+    None
+}
@@ -0,0 +1,62 @@
+use {
+    crate::error::Result,
+    chrono::{SecondsFormat, Utc},
+    serde::Serialize,
+    std::{fs::OpenOptions, io::Write, path::PathBuf},
+};
+
+/// An append-only log of destructive operations, written as one JSON object per line. The log is disabled unless a
+/// path is set.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct AuditLog {
+    /// The file entries are appended to.
+    pub(crate) path: Option<PathBuf>,
+
+    /// The ARN of the AWS principal running the operations, if it could be determined.
+    pub(crate) caller_arn: Option<String>,
+}
+
+/// A single audit log entry.
+#[derive(Debug, Serialize)]
+struct AuditEntry<'a> {
+    timestamp: String,
+    operation: &'a str,
+    instance_ids: &'a [String],
+    caller_arn: Option<&'a str>,
+    result: &'static str,
+    error: Option<String>,
+}
+
+impl AuditLog {
+    /// Append an entry recording the outcome of an operation on the instances. Failing to write the entry only prints a
+    /// warning, since the operation itself has already run.
+    pub(crate) fn record<T>(&self, operation: &str, instance_ids: &[String], result: &Result<T>) {
+        let Some(path) = &self.path else {
+            return;
+        };
+
+        let (status, error) = match result {
+            Ok(_) => ("ok", None),
+            Err(e) => ("error", Some(e.to_string())),
+        };
+
+        let entry = AuditEntry {
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            operation,
+            instance_ids,
+            caller_arn: self.caller_arn.as_deref(),
+            result: status,
+            error,
+        };
+
+        let mut line = serde_json::to_string(&entry).expect("Failed to serialize audit entry");
+        line.push('\n');
+
+        // The whole line is written at once so concurrent writers do not interleave within an entry.
+        let written =
+            OpenOptions::new().create(true).append(true).open(path).and_then(|mut f| f.write_all(line.as_bytes()));
+        if let Err(e) = written {
+            eprintln!("Warning: Failed to write audit log {}: {e}", path.display());
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use {
+    crate::error::{Error, Result},
+    aws_types::credentials::{ProvideCredentials, SharedCredentialsProvider},
+    std::future::Future,
+};
+
+/// Retries an API call once with refreshed credentials when the credentials expire partway through a run, as SSO and
+/// STS session credentials can during long batches.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct CredentialRefresher {
+    provider: Option<SharedCredentialsProvider>,
+}
+
+impl CredentialRefresher {
+    pub(crate) fn new(provider: Option<SharedCredentialsProvider>) -> Self {
+        Self {
+            provider,
+        }
+    }
+
+    /// Run the call, retrying it once if it failed because the session token expired and the credentials provider is
+    /// able to supply new credentials.
+    pub(crate) async fn retry_on_expiry<F, Fut, T, E>(&self, mut call: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = std::result::Result<T, E>>,
+        E: Into<Error>,
+    {
+        let e = match call().await {
+            Ok(output) => return Ok(output),
+            Err(e) => e.into(),
+        };
+
+        let provider = match &self.provider {
+            Some(provider) if e.is_expired_token() => provider,
+            _ => return Err(e),
+        };
+
+        // Cached credentials are refreshed once they expire, so asking the provider again yields fresh credentials.
+        let credentials = provider.provide_credentials().await.map_err(Error::AuthRefresh)?;
+        if credentials.expiry().is_none() {
+            // Static credentials cannot be refreshed.
+            return Err(e);
+        }
+
+        eprintln!("Warning: Credentials expired; retrying with refreshed credentials");
+        call().await.map_err(Into::into)
+    }
+}
@@ -2,205 +2,1938 @@ use {
     async_std_resolver::resolver_from_system_conf,
     aws_sdk_ec2::{
         self,
-        model::{Filter as Ec2Filter},
+        client::fluent_builders::DescribeInstances,
+        model::{Filter as Ec2Filter, Instance},
+        types::SdkError,
     },
-    crate::error::{NResult, Result},
-    futures::stream::{FuturesOrdered, StreamExt},
-    log::{debug, error},
+    crate::{
+        audit::AuditLog,
+        credentials::CredentialRefresher,
+        error::{Error, NResult, Result},
+        instance_set::InstanceSet,
+        progress::Progress,
+        report::{NameReport, Report, Reporter, Status},
+        timings::Timings,
+    },
+    aws_types::{region::Region, SdkConfig},
+    dialoguer::MultiSelect,
+    futures::{
+        channel::mpsc::{self, Receiver, Sender},
+        future::{self, join_all, Either},
+        stream::{self, FuturesOrdered, FuturesUnordered, Stream, StreamExt},
+        SinkExt,
+    },
+    log::{debug, error, warn},
     std::{
-        collections::HashSet,
+        borrow::Cow,
+        collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
         future::Future,
+        io::{stdout, IsTerminal},
         net::IpAddr,
-        pin::Pin,
+        pin::{pin, Pin},
+        process::Stdio,
+        sync::Arc,
+        time::Duration,
     },
+    tokio::{
+        process::Command,
+        time::{sleep, timeout, Instant},
+    },
+    trust_dns_resolver::error::ResolveErrorKind,
 };
 
-pub(crate) async fn find_instances_then<F, Ret>(ec2: aws_sdk_ec2::Client, names: Vec<String>, then: F) -> NResult
+/// The maximum number of values to send in a single describe filter.
+pub(crate) const MAX_FILTER_VALUES: usize = 200;
+
+/// The prefix of Spot Instance request status codes that signal an upcoming interruption.
+const INTERRUPTION_STATUS_PREFIX: &str = "marked-for-";
+
+/// The number of pages a name's lookup may send with `--stream` before it waits for them to be printed.
+const STREAM_PAGE_BUFFER: usize = 1;
+
+/// How long to wait between lookups of a name that resolved to no instances, with `--retry-on-empty`.
+const RETRY_ON_EMPTY_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Error codes returned by endpoints that do not support a describe filter.
+const UNSUPPORTED_FILTER_CODES: [&str; 3] = ["InvalidFilter", "InvalidParameterValue", "UnsupportedOperation"];
+
+/// The describe filters used to look up instances from a name, checked by `--check-permissions`.
+const LOOKUP_FILTERS: [&str; 8] = [
+    "ip-address",
+    "network-interface.addresses.association.public-ip",
+    "private-ip-address",
+    "network-interface.addresses.private-ip-address",
+    "network-interface.ipv6-addresses.ipv6-address",
+    "network-interface.association.carrier-ip",
+    "network-interface.mac-address",
+    "tag:Name",
+];
+
+/// Options that control how names are resolved to instances and how the results are reported.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct FindOptions {
+    /// If set, the per-name outcomes are added to this report, which is written at the end of the run.
+    pub(crate) report: Option<Arc<Reporter>>,
+
+    /// If set, refuse to run the operation unless exactly this many instances were resolved.
+    pub(crate) expected_count: Option<usize>,
+
+    /// Prefix prepended to names when falling back to a lookup by Name tag.
+    pub(crate) name_prefix: Option<String>,
+
+    /// Time spent in each phase of the run.
+    pub(crate) timings: Arc<Timings>,
+
+    /// If set, fail instead of warning when multiple names resolve to the same instance.
+    pub(crate) strict: bool,
+
+    /// If set, warn when multiple names resolve to the same instance. This is set for operations that modify
+    /// instances, where a duplicated name may mean acting on the wrong instance; read-only operations stay quiet.
+    pub(crate) warn_overlaps: bool,
+
+    /// Filters added to every describe call to limit the scope of the lookup.
+    pub(crate) base_filters: Vec<Ec2Filter>,
+
+    /// If set, names that fail to resolve are skipped with a warning instead of aborting the run. Errors from AWS API
+    /// calls still abort the run.
+    pub(crate) skip_unresolvable: bool,
+
+    /// If set, Name tags must match the name exactly; otherwise they are compared case-insensitively, like DNS names.
+    pub(crate) case_sensitive: bool,
+
+    /// If set, the maximum time to wait for a DNS lookup of a single name.
+    pub(crate) resolve_timeout: Option<Duration>,
+
+    /// If set, a shell command run to resolve host names instead of DNS; `{name}` is replaced by the name.
+    pub(crate) resolver_command: Option<String>,
+
+    /// If set, print the filters sent for each lookup to stderr.
+    pub(crate) explain: bool,
+
+    /// Retries API calls whose credentials expired partway through the run.
+    pub(crate) credentials: CredentialRefresher,
+
+    /// If set, operate on this saved set of instances instead of resolving names.
+    pub(crate) instance_set: Option<InstanceSet>,
+
+    /// Lookup filters the caller is not permitted to use; lookups using these are skipped.
+    pub(crate) denied_filters: Vec<String>,
+
+    /// Instances launched from these AMIs are excluded from lookups. EC2 has no negated filters, so this is applied
+    /// to the describe results.
+    pub(crate) excluded_image_ids: Vec<String>,
+
+    /// If set, operations resolve instances as usual but do not modify them.
+    pub(crate) readonly: bool,
+
+    /// The region being searched, recorded in the resolved instances.
+    pub(crate) region: String,
+
+    /// If set, an IP address lookup stops at the first filter that matches any instances instead of taking the union
+    /// of every filter's matches.
+    pub(crate) first_match: bool,
+
+    /// Records the destructive operations run on the resolved instances.
+    pub(crate) audit_log: AuditLog,
+
+    /// If set and standard output is a terminal, ask which instances to act on when more than one is resolved.
+    pub(crate) select: bool,
+
+    /// If set, each name is resolved and operated on separately instead of operating once on the union of the
+    /// instances of all names.
+    pub(crate) per_name: bool,
+
+    /// If set, warn when an IP address matches instances in more than one VPC, as can happen with private addresses
+    /// in peered VPCs. With `strict`, this is an error instead.
+    pub(crate) warn_cross_vpc: bool,
+
+    /// If set, print a section to stderr after the run listing the instances found by more than one name, in place
+    /// of a warning for each.
+    pub(crate) dedupe_report: bool,
+
+    /// If set, a name that resolves to no instances is looked up again until it does or this much time has passed, to
+    /// ride out EC2's eventual consistency right after a launch.
+    pub(crate) retry_on_empty: Option<Duration>,
+
+    /// Where progress messages from operations are written.
+    pub(crate) progress: Progress,
+
+    /// If set, instances in the saved set that are not in this region are looked for in every enabled region, so the
+    /// error can say where they are.
+    pub(crate) locate_set_in_all_regions: bool,
+
+    /// The SDK configuration the EC2 client was built from, used to build clients for other regions.
+    pub(crate) sdk_config: Option<SdkConfig>,
+
+    /// If set, each page of instances found while resolving a name is sent here as it arrives, for `--stream`.
+    pub(crate) page_sink: Option<PageSink>,
+}
+
+impl FindOptions {
+    /// Add a scope filter matching any of the given values. No filter is added if there are no values.
+    pub(crate) fn add_base_filter(&mut self, name: &str, values: Vec<String>) {
+        if !values.is_empty() {
+            self.base_filters.push(Ec2Filter::builder().name(name).set_values(Some(values)).build());
+        }
+    }
+
+    /// Add a scope filter from a `key=value` tag specification, or `key` to match any value of the tag.
+    pub(crate) fn add_tag_filter(&mut self, tag: &str) -> NResult {
+        match tag.split_once('=') {
+            Some((key, value)) if !key.is_empty() => self.add_base_filter(&format!("tag:{key}"), vec![value.into()]),
+            None if !tag.is_empty() => self.add_base_filter("tag-key", vec![tag.into()]),
+            _ => return Err(Error::InvalidUsage(format!("Invalid tag specification: {tag}"))),
+        }
+
+        Ok(())
+    }
+
+    /// The options without the page sink, for lookups whose pages are filtered further before they become results.
+    fn unstreamed(&self) -> Cow<'_, FindOptions> {
+        match self.page_sink {
+            Some(_) => Cow::Owned(FindOptions {
+                page_sink: None,
+                ..self.clone()
+            }),
+            None => Cow::Borrowed(self),
+        }
+    }
+}
+
+/// Where the pages of instances found for a name are sent as they arrive. The channel is bounded, so a lookup waits
+/// for its pages to be taken.
+pub(crate) type PageSink = Sender<Streamed>;
+
+/// An update on a name being resolved by `stream_resolved`.
+#[derive(Debug)]
+pub(crate) enum Streamed {
+    /// The ids of the instances from one page of a describe call. A page may repeat instances from an earlier one,
+    /// e.g. when a call is retried.
+    Page(Vec<String>),
+
+    /// The lookup finished with the ids of every instance found for the name, or failed.
+    Done(Result<Vec<String>>),
+}
+
+/// The sorted instance ids resolved from a name, or the error message if resolution failed.
+pub(crate) type NameResolution = (String, std::result::Result<Vec<String>, String>);
+
+/// Instances found by a lookup, keyed (and therefore sorted) by instance id.
+pub(crate) type InstanceMap = BTreeMap<String, Instance>;
+
+/// The instances resolved from a set of names.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ResolvedInstances {
+    /// The union of the instances resolved from every name.
+    pub(crate) instances: InstanceMap,
+
+    /// The sorted instance ids resolved from each name, in the order the names were given. Names that were skipped
+    /// because they failed to resolve are omitted.
+    pub(crate) per_name: Vec<(String, Vec<String>)>,
+
+    /// The region the instances were found in.
+    pub(crate) region: String,
+}
+
+impl ResolvedInstances {
+    /// The sorted, deduplicated instance ids of all resolved instances.
+    pub(crate) fn instance_ids(&self) -> Vec<String> {
+        self.instances.keys().cloned().collect()
+    }
+}
+
+/// Resolve the names to instances, then run the operation on the sorted, deduplicated instance ids.
+pub(crate) async fn find_instances_then<F, Ret>(
+    ec2: aws_sdk_ec2::Client,
+    options: &FindOptions,
+    names: Vec<String>,
+    then: F,
+) -> NResult
 where
-    F: FnOnce(Vec<String>) -> Ret,
+    F: FnOnce(Vec<String>) -> Ret + Clone,
     Ret: Future<Output = NResult>,
 {
-    let mut futures = FuturesOrdered::new();
+    find_resolved_then(ec2, options, names, |resolved| then(resolved.instance_ids())).await
+}
+
+/// Ask which of the resolved instances to act on, listing each with its Name tag and state, and keep only those.
+/// Selecting nothing aborts the operation.
+fn select_instances(resolved: &mut ResolvedInstances) -> NResult {
+    let instance_ids = resolved.instance_ids();
+    let items: Vec<String> = resolved
+        .instances
+        .iter()
+        .map(|(instance_id, instance)| {
+            let name = instance
+                .tags
+                .as_deref()
+                .unwrap_or_default()
+                .iter()
+                .find(|tag| tag.key.as_deref() == Some("Name"))
+                .and_then(|tag| tag.value.as_deref())
+                .unwrap_or("-");
+            let state = instance.state.as_ref().and_then(|state| state.name.as_ref()).map_or("unknown", |s| s.as_str());
+            format!("{instance_id}  {name}  {state}")
+        })
+        .collect();
+
+    let selection = MultiSelect::new()
+        .with_prompt("Select instances (space to toggle, enter to confirm)")
+        .items(&items)
+        .interact_opt()
+        .map_err(|e| Error::Runtime(format!("Failed to read selection: {e}")))?;
+
+    let selected: Vec<&String> = selection.unwrap_or_default().into_iter().map(|index| &instance_ids[index]).collect();
+    if selected.is_empty() {
+        return Err(Error::Runtime("No instances selected".to_string()));
+    }
+
+    resolved.instances.retain(|instance_id, _| selected.contains(&instance_id));
+    for (_, name_instance_ids) in resolved.per_name.iter_mut() {
+        name_instance_ids.retain(|instance_id| selected.contains(&instance_id));
+    }
+
+    Ok(())
+}
 
-    for name in names {
-        debug!("Dispatching find_instances {}", name);
-        let future = find_instances(ec2.clone(), name);
-        futures.push_back(future);
+/// Resolve the names to instances, then run the operation on the full instance records.
+pub(crate) async fn find_resolved_then<F, Ret>(
+    ec2: aws_sdk_ec2::Client,
+    options: &FindOptions,
+    names: Vec<String>,
+    then: F,
+) -> NResult
+where
+    F: FnOnce(ResolvedInstances) -> Ret + Clone,
+    Ret: Future<Output = NResult>,
+{
+    if options.per_name && options.instance_set.is_none() && names.len() > 1 {
+        find_per_name_then(ec2, options, names, then).await
+    } else {
+        find_union_then(ec2, options, names, then).await
     }
+}
 
-    let mut all_instance_ids = HashSet::new();
+/// Resolve every name, then run the operation for each name independently and concurrently, so a failure for one
+/// name does not stop the others. The outcome of each name is printed to stderr.
+///
+/// The checks over the resolved instances (overlaps, the expected count, and `--select`) and the reports cover all of
+/// the names together, as they do without `--per-name`.
+async fn find_per_name_then<F, Ret>(
+    ec2: aws_sdk_ec2::Client,
+    options: &FindOptions,
+    names: Vec<String>,
+    then: F,
+) -> NResult
+where
+    F: FnOnce(ResolvedInstances) -> Ret + Clone,
+    Ret: Future<Output = NResult>,
+{
+    let combined = combine_lookups(options, lookup_names(&ec2, options, names).await, true);
+    let resolved = match combined.resolved {
+        Ok(resolved) => resolved,
+        Err(e) => {
+            finish_run(options, &combined.per_name, combined.resolve_failed, |_| Some(e.to_string()));
+            return Err(e);
+        }
+    };
+
+    let runs = resolved.per_name.iter().map(|(name, instance_ids)| {
+        let name_resolved = ResolvedInstances {
+            instances: resolved
+                .instances
+                .iter()
+                .filter(|(instance_id, _)| instance_ids.contains(instance_id))
+                .map(|(instance_id, instance)| (instance_id.clone(), instance.clone()))
+                .collect(),
+            per_name: vec![(name.clone(), instance_ids.clone())],
+            region: resolved.region.clone(),
+        };
+        let then = then.clone();
+        async move { (name.clone(), then(name_resolved).await) }
+    });
+    let mut errors: BTreeMap<String, String> = BTreeMap::new();
+    for (name, result) in join_all(runs).await {
+        if let Err(e) = result {
+            errors.insert(name, e.to_string());
+        }
+    }
+
+    let mut failed = Vec::new();
+    for (name, resolution) in combined.per_name.iter() {
+        let error = match resolution {
+            Err(_) if combined.skipped.contains(name) => {
+                eprintln!("{name}: skipped");
+                continue;
+            }
+            Err(e) => Some(e),
+            Ok(_) => errors.get(name),
+        };
+        match error {
+            None => eprintln!("{name}: ok"),
+            Some(e) => {
+                eprintln!("{name}: {e}");
+                failed.push(name.clone());
+            }
+        }
+    }
+
+    finish_run(options, &combined.per_name, false, |name| errors.get(name).cloned());
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::NamesFailed(failed))
+    }
+}
+
+/// Resolve the names to instances, then run the operation once on the union of their instance records.
+async fn find_union_then<F, Ret>(
+    ec2: aws_sdk_ec2::Client,
+    options: &FindOptions,
+    names: Vec<String>,
+    then: F,
+) -> NResult
+where
+    F: FnOnce(ResolvedInstances) -> Ret,
+    Ret: Future<Output = NResult>,
+{
+    let combined = combine_lookups(options, lookup_names(&ec2, options, names).await, false);
+    let result = match combined.resolved {
+        Ok(resolved) => then(resolved).await,
+        Err(e) => Err(e),
+    };
+
+    let error = result.as_ref().err().map(|e| e.to_string());
+    finish_run(options, &combined.per_name, combined.resolve_failed, |_| error.clone());
+    result
+}
+
+/// The outcome of looking up each name, in the order the names were given.
+type Lookups = Vec<(String, Result<InstanceMap>)>;
+
+/// Look up every name concurrently, or the saved instance set in place of the names.
+async fn lookup_names(ec2: &aws_sdk_ec2::Client, options: &FindOptions, names: Vec<String>) -> Lookups {
+    let mut futures = FuturesOrdered::<Pin<Box<dyn Future<Output = Result<InstanceMap>>>>>::new();
+
+    // A saved instance set bypasses name resolution entirely; it is reported as if it were a single name.
+    let names = match &options.instance_set {
+        Some(instance_set) => {
+            debug!("Using saved instance set {}", instance_set.path);
+            futures.push_back(Box::pin(find_instances_in_set(ec2.clone(), options, instance_set)));
+            vec![instance_set.path.clone()]
+        }
+        None => {
+            for name in names.iter() {
+                debug!("Dispatching find_instances {}", name);
+                futures.push_back(Box::pin(find_instances(ec2.clone(), options, name.clone())));
+            }
+            names
+        }
+    };
+
+    names.into_iter().zip(futures.collect::<Vec<_>>().await).collect()
+}
+
+/// The lookups of every name, combined into the instances to operate on.
+struct Combined {
+    /// The outcome of each name, for the reports.
+    per_name: Vec<NameResolution>,
+
+    /// The names skipped under `--skip-unresolvable`.
+    skipped: Vec<String>,
+
+    /// Whether a name failed to resolve and aborted the run (as opposed to being skipped).
+    resolve_failed: bool,
+
+    /// The union of the resolved instances, or why the run cannot go ahead.
+    resolved: Result<ResolvedInstances>,
+}
+
+/// Combine the lookups into the union of their instances, then check them and, with `--select`, ask which to keep.
+///
+/// A name that fails with a resolve error is skipped with a warning under `--skip-unresolvable`; any other failure
+/// aborts the run. With `skip_failures`, every failed name is skipped silently, leaving the caller to report it.
+fn combine_lookups(options: &FindOptions, lookups: Lookups, skip_failures: bool) -> Combined {
+    let mut per_name = Vec::with_capacity(lookups.len());
+    let mut all_instances = InstanceMap::new();
     let mut first_error = None;
-    while let Some(result) = futures.next().await {
+    let mut skipped = Vec::new();
+    for (name, result) in lookups {
         match result {
-            Ok(instance_ids) => {
-                for instance_id in instance_ids {
-                    all_instance_ids.insert(instance_id);
-                }
+            Ok(instances) => {
+                let instance_ids: Vec<String> = instances.keys().cloned().collect();
+                all_instances.extend(instances);
+                per_name.push((name, Ok(instance_ids)));
             }
 
+            Err(e) if options.skip_unresolvable && e.is_resolve_failure() => {
+                eprintln!("Warning: Skipping {name}: {e}");
+                skipped.push(name.clone());
+                per_name.push((name, Err(e.to_string())));
+            }
+
+            Err(e) if skip_failures => per_name.push((name, Err(e.to_string()))),
+
             Err(e) => {
                 error!("Error finding instances: {}", e);
-                if first_error.is_none() {
-                    first_error = Some(e);
-                }
+                per_name.push((name, Err(e.to_string())));
+                first_error.get_or_insert(e);
             }
         };
     }
 
     if let Some(e) = first_error {
-        return Err(e);
+        return Combined {
+            per_name,
+            skipped,
+            resolve_failed: true,
+            resolved: Err(e),
+        };
+    }
+
+    let all_instance_ids: Vec<String> = all_instances.keys().cloned().collect();
+    let mut resolved = ResolvedInstances {
+        instances: all_instances,
+        per_name: per_name.iter().filter_map(|(name, r)| Some((name.clone(), r.as_ref().ok()?.clone()))).collect(),
+        region: options.region.clone(),
+    };
+
+    let mut checked = check_resolved(options, &per_name, &all_instance_ids);
+    if checked.is_ok() && options.select && resolved.instances.len() > 1 && stdout().is_terminal() {
+        checked = select_instances(&mut resolved);
+    }
+
+    Combined {
+        per_name,
+        skipped,
+        resolve_failed: false,
+        resolved: checked.map(|()| resolved),
     }
+}
 
-    let mut all_instance_ids: Vec<String> = all_instance_ids.into_iter().collect();
-    all_instance_ids.sort();
+/// Produce the end-of-resolution output: the `--dedupe-report` section and the entries for `--report`. `error_for`
+/// gives the operation's error, if any, for each name that resolved.
+fn finish_run(
+    options: &FindOptions,
+    per_name: &[NameResolution],
+    resolve_failed: bool,
+    error_for: impl Fn(&str) -> Option<String>,
+) {
+    if options.dedupe_report {
+        eprint!("{}", format_dedupe_report(&find_overlaps(per_name)));
+    }
 
-    then(all_instance_ids).await
+    if let Some(reporter) = &options.report {
+        reporter.add(build_report(per_name, resolve_failed, error_for));
+    }
 }
 
-pub(crate) async fn find_instances(ec2: aws_sdk_ec2::Client, name: String) -> Result<HashSet<String>> {
-    let resolver = resolver_from_system_conf().await?;
-    let mut futures = FuturesOrdered::new();
-    for ip_addr in resolver.lookup_ip(name.clone()).await? {
-        debug!("Found IP address {} for {}", ip_addr, name);
-        let future = find_instances_by_ip(ec2.clone(), ip_addr);
-        futures.push_back(future);
+/// Resolve the names with at most `concurrency` lookups in flight, yielding each name's pages of instance ids as they
+/// arrive and then its `Done` update, in name order.
+pub(crate) fn stream_resolved(
+    ec2: aws_sdk_ec2::Client,
+    options: &FindOptions,
+    names: Vec<String>,
+    concurrency: usize,
+) -> impl Stream<Item = (String, Streamed)> + '_ {
+    stream_in_name_order(names, concurrency, move |name, mut page_sink| {
+        let options = FindOptions {
+            page_sink: Some(page_sink.clone()),
+            ..options.clone()
+        };
+        let ec2 = ec2.clone();
+        async move {
+            debug!("Dispatching find_instances {}", name);
+            let result = find_instances(ec2, &options, name).await.map(|instances| instances.into_keys().collect());
+            // The channel closes once this and the options' sender are dropped, so the pages all come first.
+            let _ = page_sink.send(Streamed::Done(result)).await;
+        }
+    })
+}
+
+/// Run `lookup` for each name, at most `concurrency` at once, yielding the updates each sends in name order.
+///
+/// Only the channel of the first unfinished name is read. The lookups of the names after it run until their channels
+/// fill, then wait, so the updates held at any time are bounded, and a slow consumer holds back every lookup.
+fn stream_in_name_order<'a, F, Fut>(
+    names: Vec<String>,
+    concurrency: usize,
+    lookup: F,
+) -> impl Stream<Item = (String, Streamed)> + 'a
+where
+    F: FnMut(String, PageSink) -> Fut + 'a,
+    Fut: Future<Output = ()> + 'a,
+{
+    let state = (names.into_iter(), FuturesUnordered::new(), VecDeque::new(), lookup);
+    stream::unfold(state, move |(mut names, mut lookups, mut in_progress, mut lookup)| async move {
+        loop {
+            while in_progress.len() < concurrency {
+                let Some(name) = names.next() else {
+                    break;
+                };
+                let (page_sink, updates) = mpsc::channel(STREAM_PAGE_BUFFER);
+                lookups.push(lookup(name.clone(), page_sink));
+                in_progress.push_back((name, updates));
+            }
+
+            let (name, updates): &mut (String, Receiver<Streamed>) = in_progress.front_mut()?;
+            // The lookups are polled while waiting so that the first name's lookup makes progress.
+            let update = if lookups.is_empty() {
+                updates.next().await
+            } else {
+                match future::select(updates.next(), lookups.next()).await {
+                    Either::Left((update, _)) => update,
+                    Either::Right(_) => continue,
+                }
+            };
+
+            match update {
+                Some(update) => {
+                    let name = name.clone();
+                    return Some(((name, update), (names, lookups, in_progress, lookup)));
+                }
+                // The channel closes after the name's `Done` update, once its lookup has finished.
+                None => {
+                    in_progress.pop_front();
+                }
+            }
+        }
+    })
+}
+
+/// Resolve the names in every region enabled for the account concurrently, returning the instances found in each
+/// region that had any, ordered by region.
+///
+/// A name is resolved if it is found in any region; a resolve failure in the other regions is expected. The checks and
+/// reports run once over the names' combined results, not for each region.
+pub(crate) async fn find_resolved_in_all_regions(
+    sdk_config: &SdkConfig,
+    ec2: aws_sdk_ec2::Client,
+    options: &FindOptions,
+    names: Vec<String>,
+) -> Result<Vec<ResolvedInstances>> {
+    let regions = enabled_regions(&ec2, options).await?;
+    let searches = regions.iter().map(|region| {
+        let ec2 = regional_client(sdk_config, region);
+        let options = FindOptions {
+            region: region.clone(),
+            ..options.clone()
+        };
+        let names = names.clone();
+        async move { lookup_names(&ec2, &options, names).await }
+    });
+    let regional_lookups = join_all(searches).await;
+
+    // Keep each region's instances (and which names found them) to split the combined result by region afterwards.
+    let mut by_region: Vec<(String, Vec<(String, InstanceMap)>)> =
+        regions.iter().map(|region| (region.clone(), Vec::new())).collect();
+    let mut merged: Vec<(String, Vec<Result<InstanceMap>>)> = names.iter().map(|name| (name.clone(), vec![])).collect();
+    for (region_index, lookups) in regional_lookups.into_iter().enumerate() {
+        for (name_index, (name, result)) in lookups.into_iter().enumerate() {
+            if let Ok(instances) = &result {
+                by_region[region_index].1.push((name, instances.clone()));
+            }
+            merged[name_index].1.push(result);
+        }
+    }
+
+    let lookups = merged.into_iter().map(|(name, results)| (name, merge_regional_results(results))).collect();
+    let combined = combine_lookups(options, lookups, false);
+    let error = combined.resolved.as_ref().err().map(|e| e.to_string());
+    finish_run(options, &combined.per_name, combined.resolve_failed, |_| error.clone());
+    let resolved = combined.resolved?;
+
+    let mut results = Vec::new();
+    for (region, found) in by_region {
+        let mut regional = ResolvedInstances {
+            region,
+            ..Default::default()
+        };
+        for (name, instances) in found {
+            let instances: InstanceMap =
+                instances.into_iter().filter(|(instance_id, _)| resolved.instances.contains_key(instance_id)).collect();
+            regional.per_name.push((name, instances.keys().cloned().collect()));
+            regional.instances.extend(instances);
+        }
+
+        if !regional.instances.is_empty() {
+            results.push(regional);
+        }
+    }
+
+    Ok(results)
+}
+
+/// Merge the lookups of one name in each region: any failure other than a resolve failure is returned, otherwise the
+/// union of the instances found if any region found the name, otherwise the first resolve failure.
+fn merge_regional_results(results: Vec<Result<InstanceMap>>) -> Result<InstanceMap> {
+    let mut found = None;
+    let mut resolve_failure = None;
+    for result in results {
+        match result {
+            Ok(instances) => found.get_or_insert_with(InstanceMap::new).extend(instances),
+            Err(e) if e.is_resolve_failure() => {
+                resolve_failure.get_or_insert(e);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    match (found, resolve_failure) {
+        (Some(instances), _) => Ok(instances),
+        (None, Some(e)) => Err(e),
+        (None, None) => Ok(InstanceMap::new()),
+    }
+}
+
+/// The regions enabled for the account, sorted by name.
+async fn enabled_regions(ec2: &aws_sdk_ec2::Client, options: &FindOptions) -> Result<Vec<String>> {
+    let request = ec2.describe_regions();
+    let output = options.credentials.retry_on_expiry(|| request.clone().send()).await?;
+    let mut regions: Vec<String> =
+        output.regions.unwrap_or_default().into_iter().filter_map(|r| r.region_name).collect();
+    regions.sort();
+    Ok(regions)
+}
+
+/// An EC2 client for another region, with the same credentials and settings.
+fn regional_client(sdk_config: &SdkConfig, region: &str) -> aws_sdk_ec2::Client {
+    let ec2_config = aws_sdk_ec2::config::Builder::from(sdk_config).region(Region::new(region.to_string())).build();
+    aws_sdk_ec2::Client::from_conf(ec2_config)
+}
+
+/// Find the instances in a saved set, failing if any of them no longer exist.
+pub(crate) async fn find_instances_in_set(
+    ec2: aws_sdk_ec2::Client,
+    options: &FindOptions,
+    instance_set: &InstanceSet,
+) -> Result<InstanceMap> {
+    let instances: InstanceMap =
+        describe_instances_by_id(ec2.clone(), &options.credentials, &instance_set.instance_ids)
+            .await?
+            .into_iter()
+            .filter_map(|instance| Some((instance.instance_id.clone()?, instance)))
+            .collect();
+
+    let missing: Vec<String> =
+        instance_set.instance_ids.iter().filter(|instance_id| !instances.contains_key(*instance_id)).cloned().collect();
+    if missing.is_empty() {
+        return Ok(instances);
+    }
+
+    match &options.sdk_config {
+        Some(sdk_config) if options.locate_set_in_all_regions => {
+            Err(locate_instances(sdk_config, &ec2, options, missing).await?)
+        }
+        _ => Err(Error::MissingInstances(missing)),
+    }
+}
+
+/// Look for instances missing from this region in every other enabled region, returning an error that says which
+/// region each is in and which were not found anywhere.
+async fn locate_instances(
+    sdk_config: &SdkConfig,
+    ec2: &aws_sdk_ec2::Client,
+    options: &FindOptions,
+    instance_ids: Vec<String>,
+) -> Result<Error> {
+    let regions = enabled_regions(ec2, options).await?;
+    let searches = regions.into_iter().filter(|region| *region != options.region).map(|region| {
+        let instance_ids = &instance_ids;
+        async move {
+            let ec2 = regional_client(sdk_config, &region);
+            let instances = describe_instances_by_id(ec2, &options.credentials, instance_ids).await?;
+            Ok::<_, Error>((region, instances))
+        }
+    });
+
+    let mut located = BTreeMap::new();
+    for result in join_all(searches).await {
+        let (region, instances) = result?;
+        for instance_id in instances.into_iter().filter_map(|instance| instance.instance_id) {
+            located.insert(instance_id, region.clone());
+        }
     }
 
-    let mut all_instance_ids = HashSet::new();
+    let missing = instance_ids.into_iter().filter(|instance_id| !located.contains_key(instance_id)).collect();
+    Ok(Error::InstancesInOtherRegions {
+        region: options.region.clone(),
+        located,
+        missing,
+    })
+}
+
+/// Verify the resolved instances before the operation is run on them.
+fn check_resolved(options: &FindOptions, per_name: &[NameResolution], all_instance_ids: &[String]) -> NResult {
+    let overlaps = find_overlaps(per_name);
+    if !overlaps.is_empty() {
+        if options.strict {
+            return Err(Error::OverlappingNames(overlaps));
+        }
+
+        if options.warn_overlaps && !options.dedupe_report {
+            for (instance_id, names) in overlaps.iter() {
+                eprintln!("Warning: {} was found by multiple names: {}", instance_id, names.join(" "));
+            }
+        }
+    }
+
+    match options.expected_count {
+        Some(expected) if expected != all_instance_ids.len() => Err(Error::CountMismatch {
+            expected,
+            instance_ids: all_instance_ids.to_vec(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Format the `--dedupe-report` section: each instance found by more than one name, with the names that found it.
+pub(crate) fn format_dedupe_report(overlaps: &BTreeMap<String, Vec<String>>) -> String {
+    if overlaps.is_empty() {
+        return "No instance was found by more than one name\n".to_string();
+    }
+
+    let mut report = "Instances found by more than one name:\n".to_string();
+    for (instance_id, names) in overlaps.iter() {
+        report.push_str(&format!("  {instance_id}: {}\n", names.join(", ")));
+    }
+    report
+}
+
+/// Find the instance ids that were resolved from more than one name, along with the names that resolved to them.
+pub(crate) fn find_overlaps(per_name: &[NameResolution]) -> BTreeMap<String, Vec<String>> {
+    let mut names_by_instance_id: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (name, resolved) in per_name.iter() {
+        for instance_id in resolved.iter().flatten() {
+            let names = names_by_instance_id.entry(instance_id.clone()).or_default();
+            if !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+    }
+
+    names_by_instance_id.retain(|_, names| names.len() > 1);
+    names_by_instance_id
+}
+
+/// Combine the per-name resolution outcomes with the outcome of the operation into a report.
+///
+/// `resolve_failed` indicates that a name failed to resolve and aborted the run (as opposed to being skipped), and
+/// `error_for` gives the operation's error, if any, for each name that resolved.
+fn build_report(
+    per_name: &[NameResolution],
+    resolve_failed: bool,
+    error_for: impl Fn(&str) -> Option<String>,
+) -> Report {
+    let mut report = Report::default();
+
+    for (name, resolved) in per_name.iter().cloned() {
+        let (instance_ids, error) = match resolved {
+            Err(e) => (vec![], Some(e)),
+            Ok(instance_ids) if resolve_failed => {
+                (instance_ids, Some("Skipped due to errors resolving other names".to_string()))
+            }
+            Ok(instance_ids) => {
+                let error = error_for(&name);
+                (instance_ids, error)
+            }
+        };
+
+        let status = if error.is_some() {
+            Status::Error
+        } else {
+            Status::Ok
+        };
+
+        report.results.push(NameReport {
+            name,
+            instance_ids,
+            status,
+            error,
+        });
+    }
+
+    report
+}
+
+/// Indicates whether the argument is a full EC2 instance id (`i-` followed by 8 or 17 hex digits).
+pub(crate) fn is_instance_id(name: &str) -> bool {
+    match name.strip_prefix("i-") {
+        Some(suffix) => (suffix.len() == 8 || suffix.len() == 17) && suffix.chars().all(|c| c.is_ascii_hexdigit()),
+        None => false,
+    }
+}
+
+/// Indicates whether the argument looks like a truncated instance id: `i-` followed by fewer hex digits than a full id.
+pub(crate) fn is_partial_instance_id(name: &str) -> bool {
+    match name.strip_prefix("i-") {
+        Some(suffix) if !is_instance_id(name) => {
+            (1..17).contains(&suffix.len()) && suffix.chars().all(|c| c.is_ascii_hexdigit())
+        }
+        _ => false,
+    }
+}
+
+/// Indicates whether the argument is a Spot Fleet request id: `sfr-` followed by a UUID.
+pub(crate) fn is_spot_fleet_request_id(name: &str) -> bool {
+    let Some(uuid) = name.strip_prefix("sfr-") else {
+        return false;
+    };
+
+    let groups: Vec<&str> = uuid.split('-').collect();
+    groups.len() == 5
+        && groups
+            .iter()
+            .zip([8, 4, 4, 4, 12])
+            .all(|(group, len)| group.len() == len && group.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// The kind of target a name argument refers to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum NameKind {
+    /// A DNS name (or, failing that, a Name tag value).
+    Hostname,
+
+    /// A full instance id (e.g. `i-0123456789abcdef0`), described directly.
+    InstanceId,
+
+    /// The MAC address of a network interface (e.g. `02:ab:cd:ef:01:23`).
+    MacAddress,
+
+    /// A truncated instance id (e.g. `i-0abc`), matched against the start of full instance ids. Since such a name may
+    /// also be a host name, this is only done when DNS has no answer for it.
+    PartialInstanceId,
+
+    /// A Spot Fleet request id (`sfr-` followed by a UUID).
+    SpotFleetRequest,
+}
+
+/// Determine what kind of target a name argument refers to.
+pub(crate) fn classify_name(name: &str) -> NameKind {
+    if is_spot_fleet_request_id(name) {
+        NameKind::SpotFleetRequest
+    } else if is_instance_id(name) {
+        NameKind::InstanceId
+    } else if is_partial_instance_id(name) {
+        NameKind::PartialInstanceId
+    } else if normalize_mac_address(name).is_some() {
+        NameKind::MacAddress
+    } else {
+        NameKind::Hostname
+    }
+}
+
+/// Find instances for a name, first by resolving it through DNS and, if that yields nothing, by its Name tag.
+pub(crate) async fn find_instances(
+    ec2: aws_sdk_ec2::Client,
+    options: &FindOptions,
+    name: String,
+) -> Result<InstanceMap> {
+    let Some(retry_for) = options.retry_on_empty else {
+        return find_instances_once(ec2, options, &name).await;
+    };
+
+    let deadline = Instant::now() + retry_for;
+    loop {
+        let instances = find_instances_once(ec2.clone(), options, &name).await?;
+        if !instances.is_empty() || Instant::now() + RETRY_ON_EMPTY_INTERVAL > deadline {
+            return Ok(instances);
+        }
+
+        debug!("No instances found for {}; retrying in {:?}", name, RETRY_ON_EMPTY_INTERVAL);
+        sleep(RETRY_ON_EMPTY_INTERVAL).await;
+    }
+}
+
+async fn find_instances_once(ec2: aws_sdk_ec2::Client, options: &FindOptions, name: &str) -> Result<InstanceMap> {
+    let targets = match classify_name(name) {
+        NameKind::Hostname => resolve_targets(options, name).await,
+        NameKind::InstanceId => return find_instances_by_id(ec2, options, name).await,
+        NameKind::MacAddress => return find_instances_by_mac(ec2, options, name).await,
+        NameKind::PartialInstanceId => match resolve_targets(options, name).await {
+            Ok(targets) if !targets.is_empty() => Ok(targets),
+            Ok(_) => return find_instances_by_partial_id(ec2, options, name).await,
+            Err(e) if is_no_records(&e) => return find_instances_by_partial_id(ec2, options, name).await,
+            Err(e) => return Err(e),
+        },
+        NameKind::SpotFleetRequest => return find_instances_by_spot_fleet(ec2, options, name).await,
+    };
+
+    let dns_result = match targets {
+        Ok(targets) => {
+            let instances = find_instances_by_targets(ec2.clone(), options, name, targets).await?;
+            if !instances.is_empty() {
+                return Ok(instances);
+            }
+            Ok(instances)
+        }
+        Err(e) if is_no_records(&e) => Err(e),
+        Err(e) => return Err(e),
+    };
+
+    debug!("No instances found for {} via DNS; falling back to the Name tag", name);
+    let instances = find_instances_by_name_tag(ec2, options, name).await?;
+    if instances.is_empty() {
+        dns_result
+    } else {
+        Ok(instances)
+    }
+}
+
+/// Indicates whether the error is a DNS lookup that found no records for the name.
+fn is_no_records(e: &Error) -> bool {
+    matches!(e, Error::ResolveError(e) if matches!(e.kind(), ResolveErrorKind::NoRecordsFound { .. }))
+}
+
+/// Find the instances with the addresses or instance ids a name resolved to.
+async fn find_instances_by_targets(
+    ec2: aws_sdk_ec2::Client,
+    options: &FindOptions,
+    name: &str,
+    targets: Vec<ResolvedTarget>,
+) -> Result<InstanceMap> {
+    let mut futures: FuturesOrdered<Pin<Box<dyn Future<Output = Result<InstanceMap>>>>> = FuturesOrdered::new();
+    for target in targets {
+        match target {
+            ResolvedTarget::Address(ip_addr) => {
+                debug!("Found IP address {} for {}", ip_addr, name);
+                futures.push_back(Box::pin(find_instances_by_ip(ec2.clone(), options, ip_addr)));
+            }
+            ResolvedTarget::InstanceId(instance_id) => {
+                debug!("Found instance id {} for {}", instance_id, name);
+                let filter = Ec2Filter::builder().name("instance-id").values(instance_id).build();
+                futures.push_back(Box::pin(get_instances_by_filter(ec2.clone(), options, filter)));
+            }
+        }
+    }
+
+    let mut all_instances = InstanceMap::new();
 
     while let Some(result) = futures.next().await {
-        let result = result?;
-        for instance_id in result {
-            all_instance_ids.insert(instance_id);
+        all_instances.extend(result?);
+    }
+
+    Ok(all_instances)
+}
+
+/// Something a host name resolved to, either through DNS or the resolver command.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum ResolvedTarget {
+    Address(IpAddr),
+    InstanceId(String),
+}
+
+/// Resolve a host name through the resolver command if one is configured, otherwise through DNS.
+pub(crate) async fn resolve_targets(options: &FindOptions, name: &str) -> Result<Vec<ResolvedTarget>> {
+    match &options.resolver_command {
+        Some(command) => run_resolver_command(options, command, name).await,
+        None => Ok(lookup_addresses(options, name).await?.into_iter().map(ResolvedTarget::Address).collect()),
+    }
+}
+
+/// Run the resolver command for a name, within the configured timeout, and parse its output: one IP address or
+/// instance id per line.
+///
+/// The command is run by `sh -c`; the name is passed as a positional parameter rather than pasted into the command
+/// so it is never interpreted by the shell.
+async fn run_resolver_command(options: &FindOptions, command: &str, name: &str) -> Result<Vec<ResolvedTarget>> {
+    let script = command.replace("{name}", "\"$1\"");
+    let output = Command::new("sh").args(["-c", &script, "sh", name]).stdin(Stdio::null()).kill_on_drop(true).output();
+    let output = options.timings.time_dns(output);
+    let output = match options.resolve_timeout {
+        Some(resolve_timeout) => timeout(resolve_timeout, output).await.map_err(|_| Error::ResolveTimeout {
+            name: name.to_string(),
+            timeout: resolve_timeout,
+        })?,
+        None => output.await,
+    }
+    .map_err(|e| Error::ResolverCommand(command.to_string(), e))?;
+
+    if !output.status.success() {
+        return Err(Error::ResolverCommandFailed {
+            name: name.to_string(),
+            status: output.status,
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+
+    parse_resolver_output(name, &String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parse the output of the resolver command, ignoring blank lines.
+fn parse_resolver_output(name: &str, stdout: &str) -> Result<Vec<ResolvedTarget>> {
+    stdout
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            if is_instance_id(line) {
+                Ok(ResolvedTarget::InstanceId(line.to_string()))
+            } else if let Ok(address) = line.parse::<IpAddr>() {
+                Ok(ResolvedTarget::Address(normalize_address(address)))
+            } else {
+                Err(Error::InvalidResolverOutput {
+                    name: name.to_string(),
+                    line: line.to_string(),
+                })
+            }
+        })
+        .collect()
+}
+
+/// Look up the IP addresses of a name through DNS, within the configured timeout.
+pub(crate) async fn lookup_addresses(options: &FindOptions, name: &str) -> Result<Vec<IpAddr>> {
+    let resolver = resolver_from_system_conf().await?;
+    let lookup = options.timings.time_dns(resolver.lookup_ip(name));
+    let ip_addrs = match options.resolve_timeout {
+        Some(resolve_timeout) => timeout(resolve_timeout, lookup).await.map_err(|_| Error::ResolveTimeout {
+            name: name.to_string(),
+            timeout: resolve_timeout,
+        })??,
+        None => lookup.await?,
+    };
+
+    Ok(ip_addrs.iter().collect())
+}
+
+/// Find instances whose Name tag matches the name, prepending the configured prefix unless the name is an instance id.
+///
+/// The `tag:Name` filter is case-sensitive, so unless `options.case_sensitive` is set, a name with no exact match is
+/// compared case-insensitively against the Name tags that match `case_insensitive_pattern`, rather than every Name tag
+/// within the scope filters.
+pub(crate) async fn find_instances_by_name_tag(
+    ec2: aws_sdk_ec2::Client,
+    options: &FindOptions,
+    name: &str,
+) -> Result<InstanceMap> {
+    let tag_value = match &options.name_prefix {
+        Some(prefix) if !is_instance_id(name) => format!("{prefix}{name}"),
+        _ => name.to_string(),
+    };
+
+    let filter = Ec2Filter::builder().name("tag:Name").values(&tag_value).build();
+    let instances = get_instances_by_filter(ec2.clone(), options, filter).await?;
+    if !instances.is_empty() || options.case_sensitive {
+        return Ok(instances);
+    }
+
+    debug!("No exact Name tag match for {}; comparing case-insensitively", tag_value);
+    let filter = Ec2Filter::builder().name("tag:Name").values(case_insensitive_pattern(&tag_value)).build();
+    let mut instances = get_instances_by_filter(ec2, &options.unstreamed(), filter).await?;
+    instances.retain(|_, instance| {
+        instance.tags.as_deref().unwrap_or_default().iter().any(|tag| {
+            tag.key.as_deref() == Some("Name")
+                && tag.value.as_deref().is_some_and(|value| value.eq_ignore_ascii_case(&tag_value))
+        })
+    });
+
+    Ok(instances)
+}
+
+/// A `tag:Name` wildcard pattern matching every capitalization of the value: each ASCII letter becomes `?` and the
+/// wildcard characters are escaped. Since `?` matches any character, the matches still need to be compared with the
+/// value case-insensitively.
+fn case_insensitive_pattern(value: &str) -> String {
+    let mut pattern = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            'A'..='Z' | 'a'..='z' => pattern.push('?'),
+            '*' | '?' | '\\' => {
+                pattern.push('\\');
+                pattern.push(c);
+            }
+            _ => pattern.push(c),
         }
     }
+    pattern
+}
+
+/// Find the instance with a full instance id. The scope filters do not apply, since the id names the instance exactly.
+pub(crate) async fn find_instances_by_id(
+    ec2: aws_sdk_ec2::Client,
+    options: &FindOptions,
+    instance_id: &str,
+) -> Result<InstanceMap> {
+    let instances = describe_instances_by_id(ec2, &options.credentials, &[instance_id.to_string()]).await?;
+    Ok(instances.into_iter().filter_map(|instance| Some((instance.instance_id.clone()?, instance))).collect())
+}
+
+/// Find the single instance whose id starts with a truncated instance id, failing if more than one matches.
+pub(crate) async fn find_instances_by_partial_id(
+    ec2: aws_sdk_ec2::Client,
+    options: &FindOptions,
+    partial_id: &str,
+) -> Result<InstanceMap> {
+    let filter = Ec2Filter::builder().name("instance-id").values(format!("{partial_id}*")).build();
+    let instances = get_instances_by_filter(ec2, &options.unstreamed(), filter).await?;
+    if instances.len() > 1 {
+        return Err(Error::AmbiguousInstanceId {
+            name: partial_id.to_string(),
+            instance_ids: instances.into_keys().collect(),
+        });
+    }
+
+    Ok(instances)
+}
+
+/// Find the instances with a network interface that has the MAC address.
+pub(crate) async fn find_instances_by_mac(
+    ec2: aws_sdk_ec2::Client,
+    options: &FindOptions,
+    mac_address: &str,
+) -> Result<InstanceMap> {
+    let Some(mac_address) = normalize_mac_address(mac_address) else {
+        return Err(Error::InvalidUsage(format!("Invalid MAC address: {mac_address}")));
+    };
 
-    Ok(all_instance_ids)
+    let filter = Ec2Filter::builder().name("network-interface.mac-address").values(mac_address).build();
+    get_instances_by_filter(ec2, options, filter).await
+}
+
+/// Convert a MAC address written as six pairs of hex digits separated by colons or hyphens, or as three groups of four
+/// separated by dots, to the lowercase, colon-separated form EC2 uses. Returns `None` if it is not a MAC address.
+pub(crate) fn normalize_mac_address(s: &str) -> Option<String> {
+    let groups: Vec<&str> = if s.contains('.') {
+        s.split('.').collect()
+    } else {
+        s.split([':', '-']).collect()
+    };
+
+    let group_len = match groups.len() {
+        3 => 4,
+        6 => 2,
+        _ => return None,
+    };
+
+    if groups.iter().any(|group| group.len() != group_len || !group.chars().all(|c| c.is_ascii_hexdigit())) {
+        return None;
+    }
+
+    let digits = groups.concat().to_ascii_lowercase();
+    let pairs: Vec<&str> = (0..12).step_by(2).map(|i| &digits[i..i + 2]).collect();
+    Some(pairs.join(":"))
+}
+
+/// Find the instances that are currently active members of a Spot Fleet request.
+///
+/// A fleet that has no active instances or has expired yields no instances rather than an error.
+pub(crate) async fn find_instances_by_spot_fleet(
+    ec2: aws_sdk_ec2::Client,
+    options: &FindOptions,
+    spot_fleet_request_id: &str,
+) -> Result<InstanceMap> {
+    debug!("Describing Spot Fleet instances for {}", spot_fleet_request_id);
+
+    let mut instance_ids = Vec::new();
+    let mut next_token = None;
+
+    loop {
+        let request =
+            ec2.describe_spot_fleet_instances().spot_fleet_request_id(spot_fleet_request_id).set_next_token(next_token);
+        let output = options
+            .credentials
+            .retry_on_expiry(|| async {
+                match request.clone().send().await {
+                    Ok(output) => Ok(Some(output)),
+                    Err(SdkError::ServiceError {
+                        err,
+                        ..
+                    }) if err.code() == Some("InvalidSpotFleetRequestId.NotFound") => Ok(None),
+                    Err(e) => Err(e),
+                }
+            })
+            .await?;
+        let Some(output) = output else {
+            warn!("Spot Fleet request {} not found; it may have expired", spot_fleet_request_id);
+            return Ok(InstanceMap::new());
+        };
+
+        instance_ids.extend(output.active_instances.unwrap_or_default().into_iter().filter_map(|i| i.instance_id));
+
+        next_token = output.next_token;
+        if next_token.is_none() {
+            break;
+        }
+    }
+
+    // The fleet's instances are described with the scope filters, like every other lookup.
+    let mut instances = InstanceMap::new();
+    for chunk in instance_ids.chunks(MAX_FILTER_VALUES) {
+        let filter = Ec2Filter::builder().name("instance-id").set_values(Some(chunk.to_vec())).build();
+        instances.extend(get_instances_by_filter(ec2.clone(), options, filter).await?);
+    }
+
+    Ok(instances)
 }
 
 pub(crate) async fn find_instances_by_ip(
     ec2: aws_sdk_ec2::Client,
+    options: &FindOptions,
     address: IpAddr,
-) -> Result<HashSet<String>> {
+) -> Result<InstanceMap> {
+    let address = normalize_address(address);
     debug!("Finding instances with IP address {}", address);
-    let mut futures =
-        FuturesOrdered::<Pin<Box<dyn Future<Output = Result<HashSet<String>>>>>>::new();
-    futures.push_back(Box::pin(find_instances_by_public_ipv4(ec2.clone(), address)));
-    futures.push_back(Box::pin(find_instances_by_public_eip_ipv4(ec2.clone(), address)));
-    futures.push_back(Box::pin(find_instances_by_private_ipv4(ec2.clone(), address)));
-    futures.push_back(Box::pin(find_instances_by_private_netif_ipv4(ec2.clone(), address)));
-    futures.push_back(Box::pin(find_instances_by_netif_ipv6(ec2.clone(), address)));
+    if options.explain {
+        eprintln!("Filters for {address}:");
+    }
 
-    let mut all_instance_ids = HashSet::new();
+    if options.first_match {
+        // Take whichever lookup matches first; dropping the rest cancels their requests. Their pages are not streamed,
+        // since a lookup that loses the race is not part of the result.
+        let options = options.unstreamed();
+        let lookups = ip_lookups(&ec2, &options, address).into_iter().map(|(_, lookup)| lookup);
+        let mut futures: FuturesUnordered<_> = lookups.collect();
+        while let Some(result) = futures.next().await {
+            let instances = result?;
+            if !instances.is_empty() {
+                check_cross_vpc(&options, address, &instances)?;
+                return Ok(instances);
+            }
+        }
+
+        return Ok(InstanceMap::new());
+    }
+
+    let lookups = ip_lookups(&ec2, options, address).into_iter().map(|(_, lookup)| lookup);
+    let mut futures: FuturesOrdered<_> = lookups.collect();
+    let mut all_instances = InstanceMap::new();
 
     while let Some(result) = futures.next().await {
         match result {
-            Ok(instance_ids) => {
-                for instance_id in instance_ids {
-                    all_instance_ids.insert(instance_id);
-                }
-            }
+            Ok(instances) => all_instances.extend(instances),
             Err(e) => return Err(e),
         }
     }
 
-    Ok(all_instance_ids)
+    check_cross_vpc(options, address, &all_instances)?;
+    Ok(all_instances)
+}
+
+/// With `--warn-cross-vpc`, warn (or with `--strict`, fail) if the instances matching an address are in more than
+/// one VPC.
+fn check_cross_vpc(options: &FindOptions, address: IpAddr, instances: &InstanceMap) -> NResult {
+    if !options.warn_cross_vpc {
+        return Ok(());
+    }
+
+    let vpc_ids: BTreeSet<&str> = instances.values().filter_map(|instance| instance.vpc_id.as_deref()).collect();
+    if vpc_ids.len() < 2 {
+        return Ok(());
+    }
+
+    let vpc_ids: Vec<String> = vpc_ids.into_iter().map(str::to_string).collect();
+    if options.strict {
+        return Err(Error::CrossVpcAddress {
+            address,
+            vpc_ids,
+        });
+    }
+
+    eprintln!("Warning: {address} matches instances in {} VPCs; it may be reused across peered VPCs:", vpc_ids.len());
+    for vpc_id in vpc_ids.iter() {
+        let instance_ids: Vec<&str> = instances
+            .iter()
+            .filter(|(_, instance)| instance.vpc_id.as_deref() == Some(vpc_id.as_str()))
+            .map(|(instance_id, _)| instance_id.as_str())
+            .collect();
+        eprintln!("Warning:   {vpc_id}: {}", instance_ids.join(" "));
+    }
+
+    Ok(())
+}
+
+/// A lookup of instances by IP address, labelled with the kind of address it matches.
+pub(crate) type IpLookup<'a> = (&'static str, Pin<Box<dyn Future<Output = Result<InstanceMap>> + 'a>>);
+
+/// The lookups that together find the instances with an IP address. Only those for the address's family are run.
+pub(crate) fn ip_lookups<'a>(
+    ec2: &aws_sdk_ec2::Client,
+    options: &'a FindOptions,
+    address: IpAddr,
+) -> Vec<IpLookup<'a>> {
+    match address {
+        IpAddr::V4(_) => vec![
+            ("public-ipv4", Box::pin(find_instances_by_public_ipv4(ec2.clone(), options, address))),
+            ("public-eip-ipv4", Box::pin(find_instances_by_public_eip_ipv4(ec2.clone(), options, address))),
+            ("private-ipv4", Box::pin(find_instances_by_private_ipv4(ec2.clone(), options, address))),
+            ("private-netif-ipv4", Box::pin(find_instances_by_private_netif_ipv4(ec2.clone(), options, address))),
+            ("carrier-ip", Box::pin(find_instances_by_carrier_ip(ec2.clone(), options, address))),
+        ],
+        IpAddr::V6(_) => vec![
+            ("netif-ipv6", Box::pin(find_instances_by_netif_ipv6(ec2.clone(), options, address))),
+            ("eip-ipv6", Box::pin(find_instances_by_eip_ipv6(ec2.clone(), options, address))),
+        ],
+    }
+}
+
+/// Convert an IPv4-mapped IPv6 address (`::ffff:a.b.c.d`) to the IPv4 address it carries, since EC2 only records
+/// such addresses in the IPv4 fields. Other addresses are returned unchanged.
+pub(crate) fn normalize_address(address: IpAddr) -> IpAddr {
+    match address {
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => IpAddr::V4(v4),
+            None => address,
+        },
+        IpAddr::V4(_) => address,
+    }
 }
 
 pub(crate) async fn find_instances_by_public_ipv4(
     ec2: aws_sdk_ec2::Client,
+    options: &FindOptions,
     address: IpAddr,
-) -> Result<HashSet<String>> {
+) -> Result<InstanceMap> {
     let filter = match address {
         IpAddr::V4(addr) => Ec2Filter::builder().name("ip-address").values(addr.to_string()).build(),
-        IpAddr::V6(_) => return Ok(HashSet::new()),
+        IpAddr::V6(_) => return Ok(InstanceMap::new()),
     };
 
-    get_instance_ids_by_filter(ec2, filter).await
+    get_instances_by_filter(ec2, options, filter).await
 }
 
 pub(crate) async fn find_instances_by_public_eip_ipv4(
     ec2: aws_sdk_ec2::Client,
+    options: &FindOptions,
     address: IpAddr,
-) -> Result<HashSet<String>> {
+) -> Result<InstanceMap> {
     let filter = match address {
         IpAddr::V4(addr) => Ec2Filter::builder()
             .name("network-interface.addresses.association.public-ip")
             .values(addr.to_string())
             .build(),
-        IpAddr::V6(_) => return Ok(HashSet::new()),
+        IpAddr::V6(_) => return Ok(InstanceMap::new()),
     };
 
-    get_instance_ids_by_filter(ec2, filter).await
+    get_instances_by_filter(ec2, options, filter).await
 }
 
 pub(crate) async fn find_instances_by_private_ipv4(
     ec2: aws_sdk_ec2::Client,
+    options: &FindOptions,
     address: IpAddr,
-) -> Result<HashSet<String>> {
+) -> Result<InstanceMap> {
     let filter = match address {
         IpAddr::V4(addr) => Ec2Filter::builder().name("private-ip-address").values(addr.to_string()).build(),
-        IpAddr::V6(_) => return Ok(HashSet::new()),
+        IpAddr::V6(_) => return Ok(InstanceMap::new()),
     };
 
-    get_instance_ids_by_filter(ec2, filter).await
+    get_instances_by_filter(ec2, options, filter).await
 }
 
 pub(crate) async fn find_instances_by_private_netif_ipv4(
     ec2: aws_sdk_ec2::Client,
+    options: &FindOptions,
     address: IpAddr,
-) -> Result<HashSet<String>> {
+) -> Result<InstanceMap> {
     let filter = match address {
         IpAddr::V4(addr) => {
             Ec2Filter::builder().name("network-interface.addresses.private-ip-address").values(addr.to_string()).build()
         }
-        IpAddr::V6(_) => return Ok(HashSet::new()),
+        IpAddr::V6(_) => return Ok(InstanceMap::new()),
     };
 
-    get_instance_ids_by_filter(ec2, filter).await
+    get_instances_by_filter(ec2, options, filter).await
 }
 
 pub(crate) async fn find_instances_by_netif_ipv6(
     ec2: aws_sdk_ec2::Client,
+    options: &FindOptions,
     address: IpAddr,
-) -> Result<HashSet<String>> {
+) -> Result<InstanceMap> {
     debug!("Finding instances with IPv6 address {}", address);
 
+    match netif_ipv6_filter(address) {
+        Some(filter) => get_instances_by_ipv6_filter(ec2, options, filter).await,
+        None => Ok(InstanceMap::new()),
+    }
+}
+
+/// Find instances by an IPv6 Elastic IP address associated with them.
+///
+/// DescribeInstances can only filter on the IPv4 address of an Elastic IP association, so the address is looked up
+/// through DescribeAddresses and the instances it is associated with are then described.
+pub(crate) async fn find_instances_by_eip_ipv6(
+    ec2: aws_sdk_ec2::Client,
+    options: &FindOptions,
+    address: IpAddr,
+) -> Result<InstanceMap> {
+    let IpAddr::V6(addr) = address else {
+        return Ok(InstanceMap::new());
+    };
+
+    debug!("Finding instances with IPv6 Elastic IP address {}", address);
+    let request =
+        ec2.describe_addresses().filters(Ec2Filter::builder().name("public-ip").values(addr.to_string()).build());
+    let addresses = match options.credentials.retry_on_expiry(|| request.clone().send()).await {
+        Ok(output) => output.addresses.unwrap_or_default(),
+        Err(Error::SdkError(e)) if e.code().is_some_and(|code| UNSUPPORTED_FILTER_CODES.contains(&code)) => {
+            warn!("IPv6 Elastic IP lookup is not supported by this endpoint: {}", e);
+            return Ok(InstanceMap::new());
+        }
+        Err(e) => return Err(e),
+    };
+
+    let instance_ids: Vec<String> = addresses.into_iter().filter_map(|address| address.instance_id).collect();
+    if instance_ids.is_empty() {
+        return Ok(InstanceMap::new());
+    }
+
+    let filter = Ec2Filter::builder().name("instance-id").set_values(Some(instance_ids)).build();
+    get_instances_by_filter(ec2, options, filter).await
+}
+
+/// The filter matching an IPv6 address assigned to a network interface, or `None` for an IPv4 address.
+fn netif_ipv6_filter(address: IpAddr) -> Option<Ec2Filter> {
+    match address {
+        IpAddr::V4(_) => None,
+        IpAddr::V6(addr) => Some(
+            Ec2Filter::builder().name("network-interface.ipv6-addresses.ipv6-address").values(addr.to_string()).build(),
+        ),
+    }
+}
+
+/// Describe instances using an IPv6 filter.
+///
+/// Some older or custom endpoints reject the IPv6 filters; treat that as no match rather than failing the lookup.
+async fn get_instances_by_ipv6_filter(
+    ec2: aws_sdk_ec2::Client,
+    options: &FindOptions,
+    filter: Ec2Filter,
+) -> Result<InstanceMap> {
+    let filter_name = filter.name.clone().unwrap_or_default();
+    match get_instances_by_filter(ec2, options, filter).await {
+        Err(Error::SdkError(e)) if e.code().is_some_and(|code| UNSUPPORTED_FILTER_CODES.contains(&code)) => {
+            warn!("IPv6 lookup by {} is not supported by this endpoint: {}", filter_name, e);
+            Ok(InstanceMap::new())
+        }
+        result => result,
+    }
+}
+
+/// Find instances in Wavelength zones by the carrier IP address associated with their network interfaces.
+pub(crate) async fn find_instances_by_carrier_ip(
+    ec2: aws_sdk_ec2::Client,
+    options: &FindOptions,
+    address: IpAddr,
+) -> Result<InstanceMap> {
     let filter = match address {
-        IpAddr::V4(_) => return Ok(HashSet::new()),
-        IpAddr::V6(addr) => {
-            Ec2Filter::builder().name("network-interface.ipv6-addresses.ipv6-address").values(addr.to_string()).build()
+        IpAddr::V4(addr) => {
+            Ec2Filter::builder().name("network-interface.association.carrier-ip").values(addr.to_string()).build()
         }
+        IpAddr::V6(_) => return Ok(InstanceMap::new()),
     };
 
-    get_instance_ids_by_filter(ec2, filter).await
+    get_instances_by_filter(ec2, options, filter).await
 }
 
-pub(crate) async fn get_instance_ids_by_filter(
+pub(crate) async fn get_instances_by_filter(
     ec2: aws_sdk_ec2::Client,
+    options: &FindOptions,
     filter: Ec2Filter,
-) -> Result<HashSet<String>> {
+) -> Result<InstanceMap> {
     debug!("Describing instances with filter {:?}", filter);
 
-    let mut results = HashSet::new();
-    let mut stream = ec2.describe_instances().filters(filter).into_paginator().send();
+    let category = filter.name.clone().unwrap_or_default();
+    let denied = options.denied_filters.contains(&category);
+    if options.explain {
+        let filters: Vec<String> = std::iter::once(&filter).chain(&options.base_filters).map(format_filter).collect();
+        let mut line = filters.join(" ");
+        if denied {
+            line.push_str(" (skipped: not permitted)");
+        }
+        eprintln!("  {line}");
+    }
 
-    while let Some(describe_instances_result) = stream.next().await {
+    if denied {
+        debug!("Skipping lookup with denied filter {}", category);
+        return Ok(InstanceMap::new());
+    }
+
+    describe_instances_with_filters(ec2.describe_instances().filters(filter), options, &category).await
+}
+
+/// Format a filter as `name=value1,value2` for `--explain`.
+fn format_filter(filter: &Ec2Filter) -> String {
+    format!("{}={}", filter.name.as_deref().unwrap_or_default(), filter.values.as_deref().unwrap_or_default().join(","))
+}
+
+/// Find all instances matching the scope filters alone.
+pub(crate) async fn get_instances_in_scope(ec2: aws_sdk_ec2::Client, options: &FindOptions) -> Result<InstanceMap> {
+    debug!("Describing instances in scope");
+    describe_instances_with_filters(ec2.describe_instances(), options, "scope").await
+}
+
+/// Run a describe request with the scope filters added, retrying once if the credentials expire.
+async fn describe_instances_with_filters(
+    mut request: DescribeInstances,
+    options: &FindOptions,
+    category: &str,
+) -> Result<InstanceMap> {
+    for base_filter in options.base_filters.iter() {
+        request = request.filters(base_filter.clone());
+    }
+
+    options.credentials.retry_on_expiry(|| collect_instances(request.clone(), options, category)).await
+}
+
+/// Page through the results of a describe request, collecting the instances that are not excluded.
+///
+/// With a page sink, each page's ids are sent to it as it arrives instead, waiting until there is room, and the results
+/// hold only what the lookup itself still checks: the instance ids and VPCs (for `--warn-cross-vpc`).
+async fn collect_instances(request: DescribeInstances, options: &FindOptions, category: &str) -> Result<InstanceMap> {
+    let mut results = InstanceMap::new();
+    let mut pages = pin!(instance_pages(request, options, category));
+
+    while let Some(page) = pages.next().await {
+        let page = page?;
+        let Some(page_sink) = &options.page_sink else {
+            results.extend(page);
+            continue;
+        };
+
+        let mut instance_ids = Vec::with_capacity(page.len());
+        for (instance_id, instance) in page {
+            let stub = Instance::builder().instance_id(&instance_id).set_vpc_id(instance.vpc_id).build();
+            results.insert(instance_id.clone(), stub);
+            instance_ids.push(instance_id);
+        }
+
+        // The receiver is only gone if the stream was dropped, in which case nobody wants the page.
+        let _ = page_sink.clone().send(Streamed::Page(instance_ids)).await;
+    }
+
+    debug!("Done describing instances; results={:?}", results.keys());
+
+    Ok(results)
+}
+
+/// Page through the results of a describe request, yielding the instances of each page that are not excluded.
+fn instance_pages<'a>(
+    request: DescribeInstances,
+    options: &'a FindOptions,
+    category: &'a str,
+) -> impl Stream<Item = Result<InstanceMap>> + 'a {
+    stream::unfold(request.into_paginator().send(), move |mut stream| async move {
+        let describe_instances_result = options.timings.time_describe(category, stream.next()).await?;
         debug!("Received instances: {:?}", describe_instances_result);
-        let desribe_instances_output = describe_instances_result?;
-        for reservation in desribe_instances_output.reservations.unwrap_or(vec![]) {
-            debug!("Found reservation: {:?}", reservation.reservation_id);
-            for instance in reservation.instances.unwrap_or(vec![]) {
-                debug!("Found instance: {:?}", instance.instance_id);
-                if let Some(instance_id) = instance.instance_id {
-                    results.insert(instance_id);
+        let page = describe_instances_result.map_err(Error::from).map(|describe_instances_output| {
+            let mut page = InstanceMap::new();
+            for reservation in describe_instances_output.reservations.unwrap_or(vec![]) {
+                debug!("Found reservation: {:?}", reservation.reservation_id);
+                for instance in reservation.instances.unwrap_or(vec![]) {
+                    debug!("Found instance: {:?}", instance.instance_id);
+                    if let Some(image_id) = &instance.image_id {
+                        if options.excluded_image_ids.contains(image_id) {
+                            debug!(
+                                "Skipping instance {:?} launched from excluded image {}",
+                                instance.instance_id, image_id
+                            );
+                            continue;
+                        }
+                    }
+
+                    if let Some(instance_id) = instance.instance_id.clone() {
+                        page.insert(instance_id, instance);
+                    }
                 }
             }
+            page
+        });
+        Some((page, stream))
+    })
+}
+
+/// Issue a dry run of each lookup filter, reporting which are usable and recording the rest in
+/// `options.denied_filters` so that lookups only use the permitted filters.
+pub(crate) async fn check_permissions(ec2: &aws_sdk_ec2::Client, options: &mut FindOptions) -> NResult {
+    for filter_name in LOOKUP_FILTERS {
+        let filter = Ec2Filter::builder().name(filter_name).values("dry-run").build();
+        let request = ec2.describe_instances().filters(filter).dry_run(true);
+        let permitted = options
+            .credentials
+            .retry_on_expiry(|| async {
+                match request.clone().send().await {
+                    Ok(_) => Ok(true),
+                    Err(SdkError::ServiceError {
+                        err,
+                        ..
+                    }) if err.code() == Some("DryRunOperation") => Ok(true),
+                    Err(SdkError::ServiceError {
+                        err,
+                        ..
+                    }) if err.code() == Some("UnauthorizedOperation") => Ok(false),
+                    Err(e) => Err(e),
+                }
+            })
+            .await?;
+
+        if permitted {
+            eprintln!("{filter_name}: permitted");
+        } else {
+            eprintln!("{filter_name}: denied");
+            options.denied_filters.push(filter_name.to_string());
+        }
+    }
+
+    if options.denied_filters.len() == LOOKUP_FILTERS.len() {
+        return Err(Error::Runtime("No lookup methods are permitted".to_string()));
+    }
+
+    Ok(())
+}
+
+/// The interruption settings and current status of a Spot Instance request.
+pub(crate) struct SpotDetail {
+    pub(crate) interruption_behavior: String,
+    pub(crate) status_code: Option<String>,
+}
+
+impl SpotDetail {
+    /// The status code, if AWS has marked the instance for interruption. The notice is given two minutes before the
+    /// instance is interrupted.
+    pub(crate) fn interruption_notice(&self) -> Option<&str> {
+        self.status_code.as_deref().filter(|code| code.starts_with(INTERRUPTION_STATUS_PREFIX))
+    }
+}
+
+/// Describe the Spot Instance requests with the given ids, keyed by request id.
+pub(crate) async fn describe_spot_requests(
+    ec2: &aws_sdk_ec2::Client,
+    options: &FindOptions,
+    request_ids: Vec<String>,
+) -> Result<HashMap<String, SpotDetail>> {
+    let mut details = HashMap::new();
+    for chunk in request_ids.chunks(MAX_FILTER_VALUES) {
+        let request = ec2.describe_spot_instance_requests().set_spot_instance_request_ids(Some(chunk.to_vec()));
+        let output = options.credentials.retry_on_expiry(|| request.clone().send()).await?;
+        for spot_request in output.spot_instance_requests.unwrap_or_default() {
+            let Some(request_id) = spot_request.spot_instance_request_id else {
+                continue;
+            };
+
+            details.insert(
+                request_id,
+                SpotDetail {
+                    interruption_behavior: spot_request
+                        .instance_interruption_behavior
+                        .map_or_else(|| "terminate".to_string(), |b| b.as_str().to_string()),
+                    status_code: spot_request.status.and_then(|status| status.code),
+                },
+            );
         }
     }
 
-    debug!("Done describing instances; results={:?}", results);
+    Ok(details)
+}
+
+/// Describe the given instances by id, returning the full instance records.
+///
+/// Instances that do not exist are omitted from the results rather than causing an error.
+pub(crate) async fn describe_instances_by_id(
+    ec2: aws_sdk_ec2::Client,
+    credentials: &CredentialRefresher,
+    instance_ids: &[String],
+) -> Result<Vec<Instance>> {
+    let mut results = Vec::with_capacity(instance_ids.len());
+
+    // An empty instance id list would describe every instance in the account, so this is skipped when empty.
+    for chunk in instance_ids.chunks(MAX_FILTER_VALUES) {
+        let filter = Ec2Filter::builder().name("instance-id").set_values(Some(chunk.to_vec())).build();
+        let request = ec2.describe_instances().filters(filter);
+        results.extend(credentials.retry_on_expiry(|| collect_instance_pages(request.clone())).await?);
+    }
 
     Ok(results)
 }
 
+/// Page through the results of a describe request, collecting every instance.
+async fn collect_instance_pages(request: DescribeInstances) -> Result<Vec<Instance>> {
+    let mut results = Vec::new();
+    let mut stream = request.into_paginator().send();
+    while let Some(describe_instances_result) = stream.next().await {
+        let describe_instances_output = describe_instances_result?;
+        for reservation in describe_instances_output.reservations.unwrap_or_default() {
+            results.extend(reservation.instances.unwrap_or_default());
+        }
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{
+            case_insensitive_pattern, classify_name, find_overlaps, format_dedupe_report, ip_lookups,
+            merge_regional_results, netif_ipv6_filter, normalize_address, normalize_mac_address, parse_resolver_output,
+            stream_in_name_order, FindOptions, InstanceMap, NameKind, ResolvedTarget, Streamed,
+        },
+        crate::error::Error,
+        aws_sdk_ec2::model::Instance,
+        aws_types::region::Region,
+        futures::{SinkExt, StreamExt},
+        std::{
+            cell::RefCell,
+            net::{IpAddr, Ipv4Addr, Ipv6Addr},
+            pin::pin,
+            time::Duration,
+        },
+    };
+
+    #[test]
+    fn test_normalize_ipv4_mapped_address() {
+        let mapped: IpAddr = "::ffff:10.1.2.3".parse().unwrap();
+        assert_eq!(normalize_address(mapped), IpAddr::V4(Ipv4Addr::new(10, 1, 2, 3)));
+
+        let v4 = IpAddr::V4(Ipv4Addr::new(10, 1, 2, 3));
+        assert_eq!(normalize_address(v4), v4);
+
+        // IPv4-compatible (deprecated) and ordinary IPv6 addresses are left alone.
+        let compatible: IpAddr = "::10.1.2.3".parse().unwrap();
+        assert_eq!(normalize_address(compatible), compatible);
+        let v6 = IpAddr::V6(Ipv6Addr::new(0x2600, 0x1f18, 0, 0, 0, 0, 0, 1));
+        assert_eq!(normalize_address(v6), v6);
+    }
+
+    #[test]
+    fn test_netif_ipv6_filter() {
+        let v6 = IpAddr::V6(Ipv6Addr::new(0x2600, 0x1f18, 0, 0, 0, 0, 0, 1));
+        let filter = netif_ipv6_filter(v6).unwrap();
+        assert_eq!(filter.name.as_deref(), Some("network-interface.ipv6-addresses.ipv6-address"));
+        assert_eq!(filter.values.as_deref().unwrap(), ["2600:1f18::1"]);
+
+        assert!(netif_ipv6_filter(IpAddr::V4(Ipv4Addr::new(10, 1, 2, 3))).is_none());
+    }
+
+    #[test]
+    fn test_ip_lookups_for_address_family() {
+        let ec2 =
+            aws_sdk_ec2::Client::from_conf(aws_sdk_ec2::Config::builder().region(Region::new("us-east-1")).build());
+        let options = FindOptions::default();
+        let labels =
+            |address| ip_lookups(&ec2, &options, address).into_iter().map(|(label, _)| label).collect::<Vec<_>>();
+
+        // An IPv6 address is looked up both as an assigned address and as an Elastic IP.
+        let v6 = IpAddr::V6(Ipv6Addr::new(0x2600, 0x1f18, 0, 0, 0, 0, 0, 1));
+        assert_eq!(labels(v6), ["netif-ipv6", "eip-ipv6"]);
+
+        let v4 = IpAddr::V4(Ipv4Addr::new(10, 1, 2, 3));
+        assert_eq!(labels(v4), ["public-ipv4", "public-eip-ipv4", "private-ipv4", "private-netif-ipv4", "carrier-ip"]);
+    }
+
+    #[test]
+    fn test_case_insensitive_pattern() {
+        assert_eq!(case_insensitive_pattern("Web-01"), "???-01");
+        assert_eq!(case_insensitive_pattern("a*b?c\\"), "?\\*?\\??\\\\");
+    }
+
+    #[test]
+    fn test_normalize_mac_address() {
+        assert_eq!(normalize_mac_address("02:AB:cd:EF:01:23").as_deref(), Some("02:ab:cd:ef:01:23"));
+        assert_eq!(normalize_mac_address("02-ab-cd-ef-01-23").as_deref(), Some("02:ab:cd:ef:01:23"));
+        assert_eq!(normalize_mac_address("02ab.cdef.0123").as_deref(), Some("02:ab:cd:ef:01:23"));
+
+        assert_eq!(normalize_mac_address("02:ab:cd:ef:01"), None);
+        assert_eq!(normalize_mac_address("02:ab:cd:ef:01:2g"), None);
+        assert_eq!(normalize_mac_address("web-01.example.com"), None);
+        assert_eq!(normalize_mac_address("02ab.cdef.0123.4567"), None);
+    }
+
+    #[test]
+    fn test_parse_resolver_output() {
+        let targets = parse_resolver_output("web", "10.1.2.3\n\n  i-0123456789abcdef0\n::ffff:10.1.2.4\n").unwrap();
+        assert_eq!(
+            targets,
+            vec![
+                ResolvedTarget::Address(IpAddr::V4(Ipv4Addr::new(10, 1, 2, 3))),
+                ResolvedTarget::InstanceId("i-0123456789abcdef0".to_string()),
+                ResolvedTarget::Address(IpAddr::V4(Ipv4Addr::new(10, 1, 2, 4))),
+            ]
+        );
+        assert!(parse_resolver_output("web", "").unwrap().is_empty());
+        assert!(parse_resolver_output("web", "web.example.com\n").is_err());
+    }
+
+    #[test]
+    fn test_dedupe_report_lists_instances_found_by_several_names() {
+        let per_name = vec![
+            ("web".to_string(), Ok(vec!["i-0aaa".to_string(), "i-0bbb".to_string()])),
+            ("api".to_string(), Ok(vec!["i-0bbb".to_string()])),
+            ("db".to_string(), Err("DNS error".to_string())),
+        ];
+        assert_eq!(
+            format_dedupe_report(&find_overlaps(&per_name)),
+            "Instances found by more than one name:\n  i-0bbb: web, api\n"
+        );
+        assert_eq!(
+            format_dedupe_report(&find_overlaps(&per_name[..1])),
+            "No instance was found by more than one name\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_regional_results() {
+        let timeout = || {
+            Err(Error::ResolveTimeout {
+                name: "web".to_string(),
+                timeout: Duration::from_secs(1),
+            })
+        };
+        let found = || {
+            let mut instances = InstanceMap::new();
+            instances.insert("i-0aaa".to_string(), Instance::builder().instance_id("i-0aaa").build());
+            Ok(instances)
+        };
+
+        // A name found in one region is resolved, even though it fails to resolve in the others.
+        let merged = merge_regional_results(vec![timeout(), found(), timeout()]).unwrap();
+        assert_eq!(merged.keys().collect::<Vec<_>>(), vec!["i-0aaa"]);
+
+        // A name found nowhere fails with its resolve error; any other error fails the name regardless.
+        assert!(merge_regional_results(vec![timeout(), timeout()]).unwrap_err().is_resolve_failure());
+        let runtime = Err(Error::Runtime("denied".to_string()));
+        assert!(!merge_regional_results(vec![found(), runtime]).unwrap_err().is_resolve_failure());
+    }
+
+    #[test]
+    fn test_classify_name() {
+        assert_eq!(classify_name("i-0123456789abcdef0"), NameKind::InstanceId);
+        assert_eq!(classify_name("i-01234567"), NameKind::InstanceId);
+        assert_eq!(classify_name("i-0abc"), NameKind::PartialInstanceId);
+        assert_eq!(classify_name("sfr-12345678-abcd-4ef0-9abc-0123456789ab"), NameKind::SpotFleetRequest);
+        assert_eq!(classify_name("sfr-web01.example.com"), NameKind::Hostname);
+        assert_eq!(classify_name("sfr-12345678-abcd-4ef0-9abc-0123456789"), NameKind::Hostname);
+        assert_eq!(classify_name("02:ab:cd:ef:01:23"), NameKind::MacAddress);
+        assert_eq!(classify_name("web.example.com"), NameKind::Hostname);
+    }
+
+    #[tokio::test]
+    async fn test_stream_in_name_order() {
+        let started = RefCell::new(Vec::new());
+        let names = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let updates = stream_in_name_order(names, 2, |name, mut page_sink| {
+            started.borrow_mut().push(name.clone());
+            async move {
+                for page in 0..3 {
+                    let _ = page_sink.send(Streamed::Page(vec![format!("{name}{page}")])).await;
+                }
+                let _ = page_sink.send(Streamed::Done(Ok(vec![]))).await;
+            }
+        });
+
+        let mut updates = pin!(updates);
+        let mut seen = Vec::new();
+        while let Some((name, update)) = updates.next().await {
+            // The third name is not started until the first is done.
+            if seen.len() < 4 {
+                assert_eq!(*started.borrow(), ["a", "b"]);
+            }
+            seen.push(match update {
+                Streamed::Page(instance_ids) => instance_ids.join(","),
+                Streamed::Done(_) => format!("{name} done"),
+            });
+        }
+
+        assert_eq!(seen, ["a0", "a1", "a2", "a done", "b0", "b1", "b2", "b done", "c0", "c1", "c2", "c done"]);
+    }
+}
@@ -1,42 +1,244 @@
 use {
     async_std_resolver::ResolveError,
+    aws_http::auth::CredentialsStageError,
     aws_sdk_ec2::{
         error::{
-            CreateTagsError, DescribeInstancesError, RebootInstancesError, StartInstancesError, StopInstancesError,
-            TerminateInstancesError,
+            AssociateAddressError, CreateTagsError, DescribeAddressesError, DescribeInstancesError,
+            DescribeRegionsError, DescribeSpotFleetInstancesError, DescribeSpotInstanceRequestsError,
+            DescribeTagsError, DisassociateAddressError, ModifyInstanceAttributeError, RebootInstancesError,
+            StartInstancesError, StopInstancesError, TerminateInstancesError,
         },
         types::SdkError,
     },
-    humantime::{DurationError, TimestampError},
+    aws_sdk_organizations::error::ListAccountsError,
+    aws_sdk_sts::error::GetCallerIdentityError,
+    aws_types::credentials::CredentialsError,
+    chrono::{DateTime, Utc},
+    humantime::{format_duration, DurationError, TimestampError},
     std::{
+        collections::{BTreeMap, BTreeSet},
         error,
         fmt::{Display, Formatter, Result as FmtResult},
+        io,
+        net::IpAddr,
+        path::PathBuf,
+        process::ExitStatus,
+        time::Duration,
     },
 };
 
 #[derive(Debug)]
 pub(crate) enum Error {
+    AmbiguousInstanceId {
+        name: String,
+        instance_ids: Vec<String>,
+    },
+    Auth(Box<Ec2SdkError>),
+    AuthRefresh(CredentialsError),
+    Blackout {
+        description: Option<String>,
+        end: DateTime<Utc>,
+    },
+    CallerIdentity(Box<SdkError<GetCallerIdentityError>>),
+    CountMismatch {
+        expected: usize,
+        instance_ids: Vec<String>,
+    },
+    CrossVpcAddress {
+        address: IpAddr,
+        vpc_ids: Vec<String>,
+    },
+    InstancesInOtherRegions {
+        region: String,
+        located: BTreeMap<String, String>,
+        missing: Vec<String>,
+    },
+    InstancesNotStopped(Vec<String>),
+    InvalidCalendar(PathBuf, serde_json::Error),
     InvalidDuration(DurationError),
+    InvalidResolverOutput {
+        name: String,
+        line: String,
+    },
+    InvalidSnapshot(PathBuf, serde_json::Error),
     InvalidTime(TimestampError),
     InvalidUsage(String),
+    Io(PathBuf, io::Error),
+    ListAccounts(Box<SdkError<ListAccountsError>>),
+    MissingInstances(Vec<String>),
+    MissingRequiredTag {
+        tag: String,
+        instance_ids: Vec<String>,
+    },
+    NamesFailed(Vec<String>),
+    NoStateChange(Vec<String>),
+    OrgAccountCountMismatch {
+        expected: usize,
+        account_ids: Vec<String>,
+    },
+    OrgAccountsFailed(Vec<String>),
+    OverlappingNames(BTreeMap<String, Vec<String>>),
     #[allow(clippy::enum_variant_names)]
     ResolveError(ResolveError),
-    #[allow(dead_code)]
+    ResolveTimeout {
+        name: String,
+        timeout: Duration,
+    },
+    ResolverCommand(String, io::Error),
+    ResolverCommandFailed {
+        name: String,
+        status: ExitStatus,
+        stderr: String,
+    },
     Runtime(String),
-    SdkError(Ec2SdkError),
+    #[allow(clippy::enum_variant_names)]
+    SdkError(Box<Ec2SdkError>),
     ShowUsage,
+    UnexpectedState {
+        expected: String,
+        instances: Vec<(String, String)>,
+    },
+    UnknownInstanceIds(Vec<String>),
 }
 
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         match self {
+            Self::AmbiguousInstanceId {
+                name,
+                instance_ids,
+            } => write!(f, "{name} matches multiple instances: {}", instance_ids.join(" ")),
+            Self::Auth(e) => {
+                write!(f, "Authentication failed: {e}\nHint: check your AWS credentials or run `aws sso login`")
+            }
+            Self::AuthRefresh(e) => {
+                write!(f, "Failed to refresh expired credentials: {e}\nHint: run `aws sso login` and try again")
+            }
+            Self::Blackout {
+                description,
+                end,
+            } => {
+                write!(f, "Refusing to stop instances during a maintenance blackout")?;
+                if let Some(description) = description {
+                    write!(f, " ({description})")?;
+                }
+                write!(f, " until {}", end.to_rfc3339())
+            }
+            Self::CallerIdentity(e) => {
+                write!(f, "Failed to verify credentials: {e}\nHint: check your AWS credentials or run `aws sso login`")
+            }
+            Self::CountMismatch {
+                expected,
+                instance_ids,
+            } => write!(
+                f,
+                "Expected {expected} instance(s) but found {}: {}",
+                instance_ids.len(),
+                instance_ids.join(" ")
+            ),
+            Self::CrossVpcAddress {
+                address,
+                vpc_ids,
+            } => write!(f, "{address} matches instances in multiple VPCs: {}", vpc_ids.join(" ")),
+            Self::InstancesInOtherRegions {
+                region,
+                located,
+                missing,
+            } => {
+                write!(f, "Instances are not in {region}:")?;
+                for (instance_id, instance_region) in located.iter() {
+                    write!(f, " {instance_id} (in {instance_region})")?;
+                }
+                for instance_id in missing.iter() {
+                    write!(f, " {instance_id} (not found in any region)")?;
+                }
+
+                let regions: BTreeSet<&String> = located.values().collect();
+                if missing.is_empty() && regions.len() == 1 {
+                    write!(f, "; rerun with --region {}", regions.into_iter().next().unwrap())?;
+                }
+
+                Ok(())
+            }
+            Self::InstancesNotStopped(instance_ids) => {
+                write!(f, "Instances must be stopped first (use --stop-first): {}", instance_ids.join(" "))
+            }
+            Self::InvalidCalendar(path, e) => write!(f, "Invalid maintenance calendar {}: {e}", path.display()),
             Self::InvalidDuration(msg) => write!(f, "Invalid duration: {msg}"),
+            Self::InvalidResolverOutput {
+                name,
+                line,
+            } => write!(f, "Resolver command output for {name} is not an IP address or instance id: {line}"),
+            Self::InvalidSnapshot(path, e) => write!(f, "Invalid snapshot {}: {e}", path.display()),
             Self::InvalidTime(msg) => write!(f, "Invalid time: {msg}"),
             Self::InvalidUsage(msg) => write!(f, "Invalid usage: {msg}"),
+            Self::Io(path, e) => write!(f, "I/O error on {}: {e}", path.display()),
+            Self::ListAccounts(e) => write!(
+                f,
+                "Failed to list organization accounts: {e}\nHint: use credentials from the management account or a \
+                 delegated administrator"
+            ),
+            Self::MissingInstances(instance_ids) => {
+                write!(f, "Instances no longer exist: {}", instance_ids.join(" "))
+            }
+            Self::MissingRequiredTag {
+                tag,
+                instance_ids,
+            } => write!(f, "Refusing to act; instances lack tag {tag}: {}", instance_ids.join(" ")),
+            Self::OrgAccountCountMismatch {
+                expected,
+                account_ids,
+            } => write!(
+                f,
+                "Expected {expected} account(s) in the organization but found {}: {}",
+                account_ids.len(),
+                account_ids.join(" ")
+            ),
+            Self::NamesFailed(names) => write!(f, "Operation failed for {} name(s): {}", names.len(), names.join(" ")),
+            Self::NoStateChange(instance_ids) => {
+                write!(f, "No instances changed state; already in the target state: {}", instance_ids.join(" "))
+            }
+            Self::OrgAccountsFailed(account_ids) => {
+                write!(f, "Operation failed in {} account(s): {}", account_ids.len(), account_ids.join(" "))
+            }
+            Self::OverlappingNames(overlaps) => {
+                write!(f, "Instances were found by multiple names:")?;
+                for (instance_id, names) in overlaps.iter() {
+                    write!(f, " {} ({})", instance_id, names.join(", "))?;
+                }
+                Ok(())
+            }
             Self::ResolveError(e) => write!(f, "DNS error: {e}"),
+            Self::ResolveTimeout {
+                name,
+                timeout,
+            } => write!(f, "DNS lookup of {name} timed out after {}", format_duration(*timeout)),
+            Self::ResolverCommand(command, e) => write!(f, "Failed to run resolver command {command}: {e}"),
+            Self::ResolverCommandFailed {
+                name,
+                status,
+                stderr,
+            } => {
+                write!(f, "Resolver command failed for {name} ({status})")?;
+                if !stderr.is_empty() {
+                    write!(f, ": {stderr}")?;
+                }
+                Ok(())
+            }
             Self::Runtime(msg) => write!(f, "Runtime error: {msg}"),
             Self::SdkError(e) => write!(f, "AWS SDK error: {e}"),
             Self::ShowUsage => write!(f, "Show usage"),
+            Self::UnexpectedState {
+                expected,
+                instances,
+            } => {
+                write!(f, "Refusing to act; instances are not {expected}:")?;
+                for (instance_id, state) in instances {
+                    write!(f, " {instance_id} ({state})")?;
+                }
+                Ok(())
+            }
+            Self::UnknownInstanceIds(instance_ids) => write!(f, "Instances not found: {}", instance_ids.join(" ")),
         }
     }
 }
@@ -44,17 +246,83 @@ impl Display for Error {
 impl error::Error for Error {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match self {
+            Self::Auth(e) => Some(e.as_ref()),
+            Self::AuthRefresh(e) => Some(e),
+            Self::Blackout {
+                ..
+            } => None,
+            Self::CallerIdentity(e) => Some(e.as_ref()),
+            Self::AmbiguousInstanceId {
+                ..
+            } => None,
+            Self::CountMismatch {
+                ..
+            } => None,
+            Self::CrossVpcAddress {
+                ..
+            } => None,
+            Self::InstancesInOtherRegions {
+                ..
+            } => None,
+            Self::InstancesNotStopped(_) => None,
+            Self::InvalidCalendar(_, e) => Some(e),
             Self::InvalidDuration(msg) => Some(msg),
+            Self::InvalidResolverOutput {
+                ..
+            } => None,
+            Self::InvalidSnapshot(_, e) => Some(e),
             Self::InvalidTime(msg) => Some(msg),
             Self::InvalidUsage(_) => None,
+            Self::Io(_, e) => Some(e),
+            Self::ListAccounts(e) => Some(e.as_ref()),
+            Self::MissingInstances(_) => None,
+            Self::MissingRequiredTag {
+                ..
+            } => None,
+            Self::OrgAccountCountMismatch {
+                ..
+            } => None,
+            Self::NamesFailed(_) => None,
+            Self::NoStateChange(_) => None,
+            Self::OrgAccountsFailed(_) => None,
+            Self::OverlappingNames(_) => None,
             Self::ResolveError(e) => Some(e),
+            Self::ResolveTimeout {
+                ..
+            } => None,
+            Self::ResolverCommand(_, e) => Some(e),
+            Self::ResolverCommandFailed {
+                ..
+            } => None,
             Self::Runtime(_) => None,
-            Self::SdkError(e) => Some(e),
+            Self::SdkError(e) => Some(e.as_ref()),
             Self::ShowUsage => None,
+            Self::UnexpectedState {
+                ..
+            } => None,
+            Self::UnknownInstanceIds(_) => None,
         }
     }
 }
 
+impl Error {
+    /// Indicates whether the error came from resolving a name rather than from an AWS API call.
+    pub(crate) fn is_resolve_failure(&self) -> bool {
+        matches!(
+            self,
+            Self::InvalidResolverOutput { .. }
+                | Self::ResolveError(_)
+                | Self::ResolveTimeout { .. }
+                | Self::ResolverCommandFailed { .. }
+        )
+    }
+
+    /// Indicates whether an API call was rejected because the session token expired.
+    pub(crate) fn is_expired_token(&self) -> bool {
+        matches!(self, Self::Auth(e) if e.code() == Some("ExpiredToken"))
+    }
+}
+
 impl From<getopts::Fail> for Error {
     fn from(e: getopts::Fail) -> Self {
         Self::InvalidUsage(e.to_string())
@@ -79,47 +347,123 @@ impl From<TimestampError> for Error {
     }
 }
 
+impl From<SdkError<AssociateAddressError>> for Error {
+    fn from(e: SdkError<AssociateAddressError>) -> Self {
+        Ec2SdkError::from(e).into()
+    }
+}
+
 impl From<SdkError<CreateTagsError>> for Error {
     fn from(e: SdkError<CreateTagsError>) -> Self {
-        Self::SdkError(e.into())
+        Ec2SdkError::from(e).into()
+    }
+}
+
+impl From<SdkError<DescribeAddressesError>> for Error {
+    fn from(e: SdkError<DescribeAddressesError>) -> Self {
+        Ec2SdkError::from(e).into()
     }
 }
 
 impl From<SdkError<DescribeInstancesError>> for Error {
     fn from(e: SdkError<DescribeInstancesError>) -> Self {
-        Self::SdkError(e.into())
+        Ec2SdkError::from(e).into()
+    }
+}
+
+impl From<SdkError<DescribeRegionsError>> for Error {
+    fn from(e: SdkError<DescribeRegionsError>) -> Self {
+        Ec2SdkError::from(e).into()
+    }
+}
+
+impl From<SdkError<DescribeSpotFleetInstancesError>> for Error {
+    fn from(e: SdkError<DescribeSpotFleetInstancesError>) -> Self {
+        Ec2SdkError::from(e).into()
+    }
+}
+
+impl From<SdkError<DescribeSpotInstanceRequestsError>> for Error {
+    fn from(e: SdkError<DescribeSpotInstanceRequestsError>) -> Self {
+        Ec2SdkError::from(e).into()
+    }
+}
+
+impl From<SdkError<DescribeTagsError>> for Error {
+    fn from(e: SdkError<DescribeTagsError>) -> Self {
+        Ec2SdkError::from(e).into()
+    }
+}
+
+impl From<SdkError<DisassociateAddressError>> for Error {
+    fn from(e: SdkError<DisassociateAddressError>) -> Self {
+        Ec2SdkError::from(e).into()
+    }
+}
+
+impl From<SdkError<ModifyInstanceAttributeError>> for Error {
+    fn from(e: SdkError<ModifyInstanceAttributeError>) -> Self {
+        Ec2SdkError::from(e).into()
     }
 }
 
 impl From<SdkError<RebootInstancesError>> for Error {
     fn from(e: SdkError<RebootInstancesError>) -> Self {
-        Self::SdkError(e.into())
+        Ec2SdkError::from(e).into()
     }
 }
 
 impl From<SdkError<StartInstancesError>> for Error {
     fn from(e: SdkError<StartInstancesError>) -> Self {
-        Self::SdkError(e.into())
+        Ec2SdkError::from(e).into()
     }
 }
 
 impl From<SdkError<StopInstancesError>> for Error {
     fn from(e: SdkError<StopInstancesError>) -> Self {
-        Self::SdkError(e.into())
+        Ec2SdkError::from(e).into()
     }
 }
 
 impl From<SdkError<TerminateInstancesError>> for Error {
     fn from(e: SdkError<TerminateInstancesError>) -> Self {
-        Self::SdkError(e.into())
+        Ec2SdkError::from(e).into()
+    }
+}
+
+impl From<Ec2SdkError> for Error {
+    fn from(e: Ec2SdkError) -> Self {
+        if e.is_auth_failure() {
+            Self::Auth(Box::new(e))
+        } else {
+            Self::SdkError(Box::new(e))
+        }
     }
 }
 
+/// Service error codes indicating that the request was not authenticated or authorized.
+const AUTH_FAILURE_CODES: &[&str] = &[
+    "AuthFailure",
+    "ExpiredToken",
+    "InvalidClientTokenId",
+    "MissingAuthenticationToken",
+    "RequestExpired",
+    "UnauthorizedOperation",
+];
+
 #[derive(Debug)]
 #[allow(clippy::enum_variant_names)]
 pub(crate) enum Ec2SdkError {
+    AssociateAddressError(SdkError<AssociateAddressError>),
     CreateTagsError(SdkError<CreateTagsError>),
+    DescribeAddressesError(SdkError<DescribeAddressesError>),
     DescribeInstancesError(SdkError<DescribeInstancesError>),
+    DescribeRegionsError(SdkError<DescribeRegionsError>),
+    DescribeSpotFleetInstancesError(SdkError<DescribeSpotFleetInstancesError>),
+    DescribeSpotInstanceRequestsError(SdkError<DescribeSpotInstanceRequestsError>),
+    DescribeTagsError(SdkError<DescribeTagsError>),
+    DisassociateAddressError(SdkError<DisassociateAddressError>),
+    ModifyInstanceAttributeError(SdkError<ModifyInstanceAttributeError>),
     RebootInstancesError(SdkError<RebootInstancesError>),
     StartInstancesError(SdkError<StartInstancesError>),
     StopInstancesError(SdkError<StopInstancesError>),
@@ -129,8 +473,16 @@ pub(crate) enum Ec2SdkError {
 impl Display for Ec2SdkError {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         match self {
+            Self::AssociateAddressError(e) => write!(f, "Failed to associate Elastic IP address: {e}"),
             Self::CreateTagsError(e) => write!(f, "Failed to create tags: {e}"),
+            Self::DescribeAddressesError(e) => write!(f, "Failed to describe Elastic IP addresses: {e}"),
             Self::DescribeInstancesError(e) => write!(f, "Failed to describe instances: {e}"),
+            Self::DescribeRegionsError(e) => write!(f, "Failed to describe regions: {e}"),
+            Self::DescribeSpotFleetInstancesError(e) => write!(f, "Failed to describe Spot Fleet instances: {e}"),
+            Self::DescribeSpotInstanceRequestsError(e) => write!(f, "Failed to describe Spot Instance requests: {e}"),
+            Self::DescribeTagsError(e) => write!(f, "Failed to describe tags: {e}"),
+            Self::DisassociateAddressError(e) => write!(f, "Failed to disassociate Elastic IP address: {e}"),
+            Self::ModifyInstanceAttributeError(e) => write!(f, "Failed to modify instance attribute: {e}"),
             Self::RebootInstancesError(e) => write!(f, "Failed to reboot instances: {e}"),
             Self::StartInstancesError(e) => write!(f, "Failed to start instances: {e}"),
             Self::StopInstancesError(e) => write!(f, "Failed to stop instances: {e}"),
@@ -142,8 +494,16 @@ impl Display for Ec2SdkError {
 impl error::Error for Ec2SdkError {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match self {
+            Self::AssociateAddressError(e) => Some(e),
             Self::CreateTagsError(e) => Some(e),
+            Self::DescribeAddressesError(e) => Some(e),
             Self::DescribeInstancesError(e) => Some(e),
+            Self::DescribeRegionsError(e) => Some(e),
+            Self::DescribeSpotFleetInstancesError(e) => Some(e),
+            Self::DescribeSpotInstanceRequestsError(e) => Some(e),
+            Self::DescribeTagsError(e) => Some(e),
+            Self::DisassociateAddressError(e) => Some(e),
+            Self::ModifyInstanceAttributeError(e) => Some(e),
             Self::RebootInstancesError(e) => Some(e),
             Self::StartInstancesError(e) => Some(e),
             Self::StopInstancesError(e) => Some(e),
@@ -152,18 +512,141 @@ impl error::Error for Ec2SdkError {
     }
 }
 
+impl Ec2SdkError {
+    /// The error code returned by the service, if the service returned an error.
+    pub(crate) fn code(&self) -> Option<&str> {
+        match self {
+            Self::AssociateAddressError(e) => service_error_code(e, AssociateAddressError::code),
+            Self::CreateTagsError(e) => service_error_code(e, CreateTagsError::code),
+            Self::DescribeAddressesError(e) => service_error_code(e, DescribeAddressesError::code),
+            Self::DescribeInstancesError(e) => service_error_code(e, DescribeInstancesError::code),
+            Self::DescribeRegionsError(e) => service_error_code(e, DescribeRegionsError::code),
+            Self::DescribeSpotFleetInstancesError(e) => service_error_code(e, DescribeSpotFleetInstancesError::code),
+            Self::DescribeSpotInstanceRequestsError(e) => {
+                service_error_code(e, DescribeSpotInstanceRequestsError::code)
+            }
+            Self::DescribeTagsError(e) => service_error_code(e, DescribeTagsError::code),
+            Self::DisassociateAddressError(e) => service_error_code(e, DisassociateAddressError::code),
+            Self::ModifyInstanceAttributeError(e) => service_error_code(e, ModifyInstanceAttributeError::code),
+            Self::RebootInstancesError(e) => service_error_code(e, RebootInstancesError::code),
+            Self::StartInstancesError(e) => service_error_code(e, StartInstancesError::code),
+            Self::StopInstancesError(e) => service_error_code(e, StopInstancesError::code),
+            Self::TerminateInstancesError(e) => service_error_code(e, TerminateInstancesError::code),
+        }
+    }
+
+    /// Indicates whether the request failed because credentials were missing, expired, or rejected.
+    pub(crate) fn is_auth_failure(&self) -> bool {
+        if let Some(code) = self.code() {
+            return AUTH_FAILURE_CODES.contains(&code);
+        }
+
+        match self {
+            Self::AssociateAddressError(e) => is_credentials_failure(e),
+            Self::CreateTagsError(e) => is_credentials_failure(e),
+            Self::DescribeAddressesError(e) => is_credentials_failure(e),
+            Self::DescribeInstancesError(e) => is_credentials_failure(e),
+            Self::DescribeRegionsError(e) => is_credentials_failure(e),
+            Self::DescribeSpotFleetInstancesError(e) => is_credentials_failure(e),
+            Self::DescribeSpotInstanceRequestsError(e) => is_credentials_failure(e),
+            Self::DescribeTagsError(e) => is_credentials_failure(e),
+            Self::DisassociateAddressError(e) => is_credentials_failure(e),
+            Self::ModifyInstanceAttributeError(e) => is_credentials_failure(e),
+            Self::RebootInstancesError(e) => is_credentials_failure(e),
+            Self::StartInstancesError(e) => is_credentials_failure(e),
+            Self::StopInstancesError(e) => is_credentials_failure(e),
+            Self::TerminateInstancesError(e) => is_credentials_failure(e),
+        }
+    }
+}
+
+fn service_error_code<E>(e: &SdkError<E>, code: impl Fn(&E) -> Option<&str>) -> Option<&str> {
+    match e {
+        SdkError::ServiceError {
+            err,
+            ..
+        } => code(err),
+        _ => None,
+    }
+}
+
+/// Indicates whether the request could not be constructed because credentials could not be loaded.
+fn is_credentials_failure<E>(e: &SdkError<E>) -> bool {
+    let SdkError::ConstructionFailure(e) = e else {
+        return false;
+    };
+
+    let mut source: Option<&(dyn error::Error + 'static)> = Some(e.as_ref());
+    while let Some(e) = source {
+        if e.is::<CredentialsStageError>() || e.is::<CredentialsError>() {
+            return true;
+        }
+        source = e.source();
+    }
+
+    false
+}
+
+impl From<SdkError<AssociateAddressError>> for Ec2SdkError {
+    fn from(e: SdkError<AssociateAddressError>) -> Self {
+        Self::AssociateAddressError(e)
+    }
+}
+
 impl From<SdkError<CreateTagsError>> for Ec2SdkError {
     fn from(e: SdkError<CreateTagsError>) -> Self {
         Self::CreateTagsError(e)
     }
 }
 
+impl From<SdkError<DescribeAddressesError>> for Ec2SdkError {
+    fn from(e: SdkError<DescribeAddressesError>) -> Self {
+        Self::DescribeAddressesError(e)
+    }
+}
+
 impl From<SdkError<DescribeInstancesError>> for Ec2SdkError {
     fn from(e: SdkError<DescribeInstancesError>) -> Self {
         Self::DescribeInstancesError(e)
     }
 }
 
+impl From<SdkError<DescribeRegionsError>> for Ec2SdkError {
+    fn from(e: SdkError<DescribeRegionsError>) -> Self {
+        Self::DescribeRegionsError(e)
+    }
+}
+
+impl From<SdkError<DescribeSpotFleetInstancesError>> for Ec2SdkError {
+    fn from(e: SdkError<DescribeSpotFleetInstancesError>) -> Self {
+        Self::DescribeSpotFleetInstancesError(e)
+    }
+}
+
+impl From<SdkError<DescribeSpotInstanceRequestsError>> for Ec2SdkError {
+    fn from(e: SdkError<DescribeSpotInstanceRequestsError>) -> Self {
+        Self::DescribeSpotInstanceRequestsError(e)
+    }
+}
+
+impl From<SdkError<DescribeTagsError>> for Ec2SdkError {
+    fn from(e: SdkError<DescribeTagsError>) -> Self {
+        Self::DescribeTagsError(e)
+    }
+}
+
+impl From<SdkError<DisassociateAddressError>> for Ec2SdkError {
+    fn from(e: SdkError<DisassociateAddressError>) -> Self {
+        Self::DisassociateAddressError(e)
+    }
+}
+
+impl From<SdkError<ModifyInstanceAttributeError>> for Ec2SdkError {
+    fn from(e: SdkError<ModifyInstanceAttributeError>) -> Self {
+        Self::ModifyInstanceAttributeError(e)
+    }
+}
+
 impl From<SdkError<RebootInstancesError>> for Ec2SdkError {
     fn from(e: SdkError<RebootInstancesError>) -> Self {
         Self::RebootInstancesError(e)
@@ -0,0 +1,133 @@
+use {crate::error::Error, std::process::ExitCode};
+
+/// The operation failed for a reason not covered by a more specific code.
+pub(crate) const FAILURE: u8 = 1;
+
+/// The command line was invalid.
+pub(crate) const INVALID_USAGE: u8 = 2;
+
+/// A name could not be resolved to instances.
+pub(crate) const RESOLVE_FAILURE: u8 = 3;
+
+/// A DNS lookup timed out.
+pub(crate) const TIMEOUT: u8 = 4;
+
+/// AWS rejected or could not obtain credentials.
+pub(crate) const AUTH_FAILURE: u8 = 5;
+
+/// A safety check (--calendar, --confirm-count, --expect-state, --only-if-tagged, --org-confirm-accounts, --strict)
+/// refused to act, or an address matched instances in more than one VPC.
+pub(crate) const REFUSED: u8 = 6;
+
+/// Instances in a saved set no longer exist.
+pub(crate) const MISSING_INSTANCES: u8 = 7;
+
+/// No instance changed state because all were already in the target state (--expect-change).
+pub(crate) const NO_CHANGE: u8 = 8;
+
+/// Every exit code other than success, with a description, as printed by `--print-exit-codes`.
+const EXIT_CODES: &[(u8, &str)] = &[
+    (FAILURE, "failure (including AWS API errors)"),
+    (INVALID_USAGE, "invalid usage"),
+    (RESOLVE_FAILURE, "a name could not be resolved or was ambiguous"),
+    (TIMEOUT, "a DNS lookup timed out"),
+    (AUTH_FAILURE, "authentication failed or credentials could not be refreshed"),
+    (REFUSED, "a safety check refused to act"),
+    (MISSING_INSTANCES, "instances in a saved set no longer exist"),
+    (NO_CHANGE, "no instance changed state (--expect-change)"),
+];
+
+/// The process exit code for an error.
+pub(crate) fn exit_code_for(e: &Error) -> ExitCode {
+    let code = match e {
+        Error::ShowUsage => return ExitCode::SUCCESS,
+        Error::InvalidUsage(_) => INVALID_USAGE,
+        Error::AmbiguousInstanceId {
+            ..
+        }
+        | Error::InvalidResolverOutput {
+            ..
+        }
+        | Error::ResolveError(_)
+        | Error::ResolverCommandFailed {
+            ..
+        }
+        | Error::UnknownInstanceIds(_) => RESOLVE_FAILURE,
+        Error::ResolveTimeout {
+            ..
+        } => TIMEOUT,
+        Error::Auth(_) | Error::AuthRefresh(_) | Error::CallerIdentity(_) => AUTH_FAILURE,
+        Error::Blackout {
+            ..
+        }
+        | Error::CountMismatch {
+            ..
+        }
+        | Error::CrossVpcAddress {
+            ..
+        }
+        | Error::MissingRequiredTag {
+            ..
+        }
+        | Error::OrgAccountCountMismatch {
+            ..
+        }
+        | Error::OverlappingNames(_)
+        | Error::InstancesNotStopped(_)
+        | Error::UnexpectedState {
+            ..
+        } => REFUSED,
+        Error::InstancesInOtherRegions {
+            ..
+        }
+        | Error::MissingInstances(_) => MISSING_INSTANCES,
+        Error::NoStateChange(_) => NO_CHANGE,
+        Error::InvalidCalendar(..)
+        | Error::InvalidDuration(_)
+        | Error::InvalidSnapshot(..)
+        | Error::InvalidTime(_)
+        | Error::Io(..)
+        | Error::ListAccounts(_)
+        | Error::NamesFailed(_)
+        | Error::OrgAccountsFailed(_)
+        | Error::ResolverCommand(..)
+        | Error::Runtime(_)
+        | Error::SdkError(_) => FAILURE,
+    };
+
+    ExitCode::from(code)
+}
+
+/// Print the table of exit codes.
+pub(crate) fn print_exit_codes() {
+    println!("0\tsuccess");
+    for (code, description) in EXIT_CODES {
+        println!("{code}\t{description}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{exit_code_for, INVALID_USAGE, REFUSED, RESOLVE_FAILURE},
+        crate::error::Error,
+        std::process::ExitCode,
+    };
+
+    #[test]
+    fn test_exit_code_for() {
+        assert_eq!(exit_code_for(&Error::ShowUsage), ExitCode::SUCCESS);
+        assert_eq!(exit_code_for(&Error::InvalidUsage("bad".to_string())), ExitCode::from(INVALID_USAGE));
+        assert_eq!(
+            exit_code_for(&Error::UnknownInstanceIds(vec!["i-0123456789abcdef0".to_string()])),
+            ExitCode::from(RESOLVE_FAILURE)
+        );
+
+        // An address shared across VPCs is refused rather than treated as unresolvable.
+        let cross_vpc = Error::CrossVpcAddress {
+            address: "10.0.0.1".parse().unwrap(),
+            vpc_ids: vec!["vpc-1".to_string(), "vpc-2".to_string()],
+        };
+        assert_eq!(exit_code_for(&cross_vpc), ExitCode::from(REFUSED));
+    }
+}
@@ -0,0 +1,69 @@
+use {
+    crate::error::{Error, NResult, Result},
+    aws_sdk_ec2::model::Instance,
+    chrono::{DateTime, Utc},
+    serde::Deserialize,
+    std::fs,
+};
+
+/// A set of blackout windows during which instances must not be stopped, read from a JSON file of the form
+/// `{"windows": [{"start": "<rfc3339>", "end": "<rfc3339>", "description": "..."}]}`. The description is optional.
+///
+/// The calendar takes precedence over tags: while a window is active, no instance may be stopped, whatever its tags
+/// say. Outside the windows, the NoStopBefore and DoNotStop tags protect individual instances as usual.
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct MaintenanceCalendar {
+    pub(crate) windows: Vec<BlackoutWindow>,
+}
+
+/// A period, from `start` (inclusive) to `end` (exclusive), during which instances must not be stopped.
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct BlackoutWindow {
+    pub(crate) start: DateTime<Utc>,
+    pub(crate) end: DateTime<Utc>,
+    #[serde(default)]
+    pub(crate) description: Option<String>,
+}
+
+impl MaintenanceCalendar {
+    /// Read a calendar file, rejecting windows that end before they start.
+    pub(crate) fn read(path: &str) -> Result<Self> {
+        let contents = fs::read_to_string(path).map_err(|e| Error::Io(path.into(), e))?;
+        let calendar: Self = serde_json::from_str(&contents).map_err(|e| Error::InvalidCalendar(path.into(), e))?;
+
+        if let Some(window) = calendar.windows.iter().find(|window| window.end < window.start) {
+            return Err(Error::InvalidUsage(format!("{path}: window starting {} ends before it starts", window.start)));
+        }
+
+        Ok(calendar)
+    }
+
+    /// The blackout window in effect at the given time, if any.
+    pub(crate) fn active_window(&self, now: DateTime<Utc>) -> Option<&BlackoutWindow> {
+        self.windows.iter().find(|window| window.start <= now && now < window.end)
+    }
+
+    /// Refuse to stop instances if a blackout window is in effect at the given time.
+    pub(crate) fn check_stop(&self, now: DateTime<Utc>) -> NResult {
+        match self.active_window(now) {
+            Some(window) => Err(Error::Blackout {
+                description: window.description.clone(),
+                end: window.end,
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Indicates whether an instance is protected from being stopped, either by a DoNotStop tag or by a NoStopBefore tag
+/// in the future. A NoStopBefore tag that cannot be parsed is treated as protecting the instance.
+pub(crate) fn is_stop_protected(instance: &Instance, now: DateTime<Utc>) -> bool {
+    instance.tags.as_deref().unwrap_or_default().iter().any(|tag| match tag.key.as_deref() {
+        Some("DoNotStop") => true,
+        Some("NoStopBefore") => match DateTime::parse_from_rfc3339(tag.value.as_deref().unwrap_or_default()) {
+            Ok(no_stop_before) => no_stop_before > now,
+            Err(_) => true,
+        },
+        _ => false,
+    })
+}
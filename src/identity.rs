@@ -0,0 +1,22 @@
+use {
+    crate::error::{Error, Result},
+    aws_types::SdkConfig,
+};
+
+/// The AWS principal the tool is acting as.
+#[derive(Clone, Debug)]
+pub(crate) struct CallerIdentity {
+    pub(crate) arn: String,
+}
+
+impl CallerIdentity {
+    /// Look up the caller identity through STS. This is an extra round trip, so it is only done when requested.
+    pub(crate) async fn fetch(sdk_config: &SdkConfig) -> Result<Self> {
+        let sts = aws_sdk_sts::Client::new(sdk_config);
+        let output = sts.get_caller_identity().send().await.map_err(|e| Error::CallerIdentity(Box::new(e)))?;
+
+        Ok(Self {
+            arn: output.arn.unwrap_or_default(),
+        })
+    }
+}
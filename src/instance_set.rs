@@ -0,0 +1,49 @@
+use {
+    crate::{
+        ec2::is_instance_id,
+        error::{Error, NResult, Result},
+    },
+    std::{fs, path::Path},
+};
+
+/// A set of instance ids saved by a previous run, used in place of resolving names.
+#[derive(Clone, Debug)]
+pub(crate) struct InstanceSet {
+    /// The file the set was read from.
+    pub(crate) path: String,
+
+    /// The instance ids in the set.
+    pub(crate) instance_ids: Vec<String>,
+}
+
+impl InstanceSet {
+    /// Read a set file containing one instance id per line. Blank lines are ignored.
+    pub(crate) fn read(path: &str) -> Result<Self> {
+        let contents = fs::read_to_string(path).map_err(|e| Error::Io(path.into(), e))?;
+        let mut instance_ids = Vec::new();
+
+        for line in contents.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            if !is_instance_id(line) {
+                return Err(Error::InvalidUsage(format!("{path}: not an instance id: {line}")));
+            }
+
+            instance_ids.push(line.to_string());
+        }
+
+        Ok(Self {
+            path: path.to_string(),
+            instance_ids,
+        })
+    }
+
+    /// Write instance ids to a set file, one per line.
+    pub(crate) fn write(path: &Path, instance_ids: &[String]) -> NResult {
+        let mut contents = String::new();
+        for instance_id in instance_ids {
+            contents.push_str(instance_id);
+            contents.push('\n');
+        }
+
+        fs::write(path, contents).map_err(|e| Error::Io(path.into(), e))
+    }
+}
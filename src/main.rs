@@ -1,31 +1,166 @@
+mod audit;
+mod credentials;
 mod ec2;
 mod error;
+mod exit_codes;
+mod guard;
+mod identity;
+mod instance_set;
+#[cfg(feature = "metrics")]
+mod metrics;
 mod ops;
+mod org;
+mod pricing;
+mod progress;
+mod report;
+mod snapshot;
+mod timings;
 
 use {
-    crate::error::Error,
+    crate::{
+        audit::AuditLog,
+        credentials::CredentialRefresher,
+        ec2::{check_permissions, FindOptions},
+        error::{Error, NResult},
+        exit_codes::{exit_code_for, print_exit_codes, INVALID_USAGE},
+        identity::CallerIdentity,
+        progress::Progress,
+        report::{ReportFormat, Reporter},
+    },
     aws_config::{self, profile::ProfileFileCredentialsProvider},
-    aws_types::region::Region,
+    aws_sdk_ec2::model::InstanceStateName,
+    aws_types::{region::Region, SdkConfig},
+    futures::future::join_all,
     getopts::{Options, ParsingStyle},
+    humantime::parse_duration,
     std::{
+        collections::BTreeMap,
         env,
-        io::{stderr, stdout, Write},
+        fmt::Display,
+        io::{stderr, stdin, stdout, Write},
+        path::PathBuf,
         process::ExitCode,
+        sync::Arc,
+        time::Duration,
     },
 };
 
-const INVALID_USAGE: u8 = 2;
+/// The values accepted by --tenancy.
+const TENANCIES: [&str; 3] = ["dedicated", "default", "host"];
+
+/// The operations that require AWS access, in dispatch order.
+const OPERATIONS: [&str; 15] = [
+    "associate-eip",
+    "diagnose",
+    "disassociate-eip",
+    "find",
+    "ips",
+    "print",
+    "reboot",
+    "set-no-stop-before",
+    "set-user-data",
+    "start",
+    "status",
+    "stop",
+    "tags",
+    "terminate",
+    "unprotect",
+];
+
+/// The operations that never modify instances, and so may run across an organization without --org-confirm-accounts.
+const READ_ONLY_OPERATIONS: [&str; 6] = ["diagnose", "find", "ips", "print", "status", "tags"];
+
+/// The environment variable that, when set to 1, makes every operation leave instances unchanged. This is a guardrail
+/// for locked-down environments and cannot be overridden on the command line.
+const READONLY_ENV_VAR: &str = "EC2_BY_NAME_READONLY";
+
+/// The role assumed in each member account with --org-accounts unless --org-role is given. AWS Organizations creates
+/// this role in accounts created through it.
+const DEFAULT_ORG_ROLE: &str = "OrganizationAccountAccessRole";
+
+/// How long --retry-on-empty keeps looking up a name unless --retry-for is given.
+const DEFAULT_RETRY_ON_EMPTY_DURATION: Duration = Duration::from_secs(30);
 
 #[tokio::main]
 async fn main() -> ExitCode {
     env_logger::init();
     let args: Vec<String> = env::args().collect();
+
+    // Hidden option for wrapper scripts; only recognized as the first argument so it never collides with names.
+    if args.get(1).map(String::as_str) == Some("--print-exit-codes") {
+        print_exit_codes();
+        return ExitCode::SUCCESS;
+    }
+
     let mut opts = Options::new();
     opts.parsing_style(ParsingStyle::StopAtFirstFree);
     opts.optopt("p", "profile", "Use AWS credentials from the specified profile in ~/.aws/credentials", "<profile>");
 
+    opts.optopt("", "audit-log", "Append a JSON line for each destructive operation to this file", "<path>");
+    opts.optmulti("", "az", "Only match instances in the specified availability zone (repeatable)", "<zone>");
+    opts.optflag("", "case-sensitive", "Match names against Name tags case-sensitively");
+    opts.optflag("", "check-permissions", "Check which lookup methods are permitted and use only those");
+    opts.optflag("", "dedupe-report", "After the run, list instances found by more than one name, on stderr");
+    opts.optflag("", "explain", "Print the EC2 filters used for each lookup to stderr");
+    opts.optflag("", "first-match", "Stop looking up an IP address at the first lookup method that matches");
     opts.optflag("h", "help", "Print this help menu");
+    opts.optopt("", "iam-profile", "Only match instances with the specified IAM instance profile", "<name-or-arn>");
+    opts.optmulti("", "image-id", "Only match instances launched from the specified AMI (repeatable)", "<ami-id>");
+    opts.optmulti("", "key-name", "Only match instances launched with the specified key pair (repeatable)", "<name>");
+    opts.optopt("", "name-prefix", "Prefix to prepend to names when looking up instances by Name tag", "<prefix>");
+    opts.optmulti("", "not-image-id", "Exclude instances launched from the specified AMI (repeatable)", "<ami-id>");
+    opts.optflag("", "org-accounts", "Run the operation in every active account in the AWS Organization");
+    opts.optopt(
+        "",
+        "org-confirm-accounts",
+        "With --org-accounts, refuse to modify instances unless the organization has exactly this many accounts",
+        "<count>",
+    );
+    opts.optopt(
+        "",
+        "org-role",
+        "With --org-accounts, the role name or ARN to assume in each account; {account} is replaced by the account id",
+        "<template>",
+    );
+    opts.optflag("", "per-name", "Resolve and operate on each name separately, reporting the outcome of each");
     opts.optopt("r", "region", "Use specified AWS region", "<region>");
+    opts.optmulti(
+        "",
+        "profile-region-map",
+        "Default regions for profiles, used when the profile is selected and --region is not given (repeatable)",
+        "<profile>=<region>,...",
+    );
+    opts.optopt("", "progress-to", "Write progress messages to stderr (default) or stdout", "<stream>");
+    opts.optopt("", "resolve-timeout", "Maximum time to wait for the DNS lookup of each name", "<duration>");
+    opts.optopt(
+        "",
+        "resolver-command",
+        "Resolve host names by running this shell command instead of DNS; {name} is replaced by the name",
+        "<command>",
+    );
+    opts.optflag("", "retry-on-empty", "Retry lookups of names that match no instances until --retry-for elapses");
+    opts.optopt("", "retry-for", "With --retry-on-empty, how long to keep retrying (default 30s)", "<duration>");
+    opts.optflag("", "select", "When several instances are resolved and output is a terminal, ask which to act on");
+    opts.optflag("", "skip-unresolvable", "Skip names that fail to resolve; AWS API errors still abort");
+    opts.optmulti("", "state", "Only match instances in the specified state, e.g. running (repeatable)", "<state>");
+    opts.optflag("", "stdin", "Read additional names, one per line, from standard input");
+    opts.optflag("", "stdin-first", "Place names read from standard input before names on the command line");
+    opts.optflag("", "stdin-last", "Place names read from standard input after names on the command line (default)");
+    opts.optmulti(
+        "",
+        "tenancy",
+        "Only match instances with the specified tenancy: dedicated, default, or host (repeatable)",
+        "<tenancy>",
+    );
+    opts.optmulti("", "tag", "Only match instances with the specified tag (repeatable)", "<key>[=<value>]");
+    opts.optflag("", "strict", "Fail instead of warning about instances found by several names or IPs in several VPCs");
+    opts.optflag("", "timings", "Print a breakdown of time spent in each phase to stderr at the end");
+    opts.optflag("v", "verbose", "Print the AWS identity and region being used at startup");
+    opts.optflag("", "verify-credentials", "Verify the AWS credentials and print the identity before running");
+    opts.optmulti("", "vpc-id", "Only match instances in the specified VPC (repeatable)", "<vpc-id>");
+    opts.optflag("", "warn-cross-vpc", "Warn when an IP address matches instances in more than one VPC");
+    opts.optopt("", "report", "Print a summary report of the run to stderr in the specified format (json)", "<format>");
+    opts.optopt("", "report-file", "With --report, write the report to this file instead of stderr", "<path>");
 
     let matches = match opts.parse(&args[1..]) {
         Ok(m) => m,
@@ -49,8 +184,137 @@ async fn main() -> ExitCode {
         return ExitCode::from(INVALID_USAGE);
     }
 
+    let report = match matches.opt_str("report").map(|format| format.parse::<ReportFormat>()).transpose() {
+        Ok(report) => report,
+        Err(e) => return usage_error(opts, e),
+    };
+    if report.is_none() && matches.opt_present("report-file") {
+        return usage_error(opts, "--report-file requires --report");
+    }
+    let report =
+        report.map(|format| Arc::new(Reporter::new(format, matches.opt_str("report-file").map(PathBuf::from))));
+
+    let progress = match matches.opt_str("progress-to").map(|stream| stream.parse::<Progress>()).transpose() {
+        Ok(progress) => progress.unwrap_or_default(),
+        Err(e) => return usage_error(opts, e),
+    };
+
+    if matches.opt_present("stdin-first") && matches.opt_present("stdin-last") {
+        return usage_error(opts, "Cannot specify both --stdin-first and --stdin-last");
+    }
+
+    let tenancies = matches.opt_strs("tenancy");
+    if let Some(tenancy) = tenancies.iter().find(|t| !TENANCIES.contains(&t.as_str())) {
+        return usage_error(opts, format!("Unknown tenancy: {tenancy}"));
+    }
+
+    let states = matches.opt_strs("state");
+    let unknown_state =
+        |state: &&String| matches!(InstanceStateName::from(state.as_str()), InstanceStateName::Unknown(_));
+    if let Some(state) = states.iter().find(unknown_state) {
+        return usage_error(opts, format!("Unknown instance state: {state}"));
+    }
+
+    let resolve_timeout = match matches.opt_str("resolve-timeout").map(|d| parse_duration(&d)).transpose() {
+        Ok(resolve_timeout) => resolve_timeout,
+        Err(e) => return usage_error(opts, Error::from(e)),
+    };
+
+    let retry_for = match matches.opt_str("retry-for").map(|d| parse_duration(&d)).transpose() {
+        Ok(retry_for) => retry_for,
+        Err(e) => return usage_error(opts, Error::from(e)),
+    };
+    if retry_for.is_some() && !matches.opt_present("retry-on-empty") {
+        return usage_error(opts, "--retry-for requires --retry-on-empty");
+    }
+    let retry_on_empty =
+        matches.opt_present("retry-on-empty").then(|| retry_for.unwrap_or(DEFAULT_RETRY_ON_EMPTY_DURATION));
+
+    let mut options = FindOptions {
+        report,
+        name_prefix: matches.opt_str("name-prefix"),
+        strict: matches.opt_present("strict"),
+        case_sensitive: matches.opt_present("case-sensitive"),
+        skip_unresolvable: matches.opt_present("skip-unresolvable"),
+        explain: matches.opt_present("explain"),
+        first_match: matches.opt_present("first-match"),
+        select: matches.opt_present("select"),
+        per_name: matches.opt_present("per-name"),
+        warn_cross_vpc: matches.opt_present("warn-cross-vpc"),
+        dedupe_report: matches.opt_present("dedupe-report"),
+        retry_on_empty,
+        progress,
+        audit_log: AuditLog {
+            path: matches.opt_str("audit-log").map(PathBuf::from),
+            caller_arn: None,
+        },
+        resolve_timeout,
+        resolver_command: matches.opt_str("resolver-command"),
+        excluded_image_ids: matches.opt_strs("not-image-id"),
+        readonly: env::var(READONLY_ENV_VAR).as_deref() == Ok("1"),
+        ..Default::default()
+    };
+    options.add_base_filter("availability-zone", matches.opt_strs("az"));
+    options.add_base_filter("image-id", matches.opt_strs("image-id"));
+    options.add_base_filter("key-name", matches.opt_strs("key-name"));
+    options.add_base_filter("tenancy", tenancies);
+    options.add_base_filter("vpc-id", matches.opt_strs("vpc-id"));
+    options.add_base_filter("instance-state-name", states);
+    for tag in matches.opt_strs("tag") {
+        if let Err(e) = options.add_tag_filter(&tag) {
+            return usage_error(opts, e);
+        }
+    }
+    if let Some(profile) = matches.opt_str("iam-profile") {
+        options.add_base_filter("iam-instance-profile.arn", instance_profile_arn_patterns(&profile));
+    }
+    let timings = matches.opt_present("timings").then(|| options.timings.clone());
+    let reporter = options.report.clone();
+
+    let (op_name, op_args) = matches.free.split_first().unwrap();
+    let mut op_args = op_args.to_vec();
+
+    // Operations that don't need AWS access are dispatched before the (potentially slow) AWS configuration load.
+    if op_name == "version" {
+        return exit_code(opts, ops::version::print_version(op_args));
+    }
+
+    if !OPERATIONS.contains(&op_name.as_str()) {
+        eprintln!("Unknown operation {}", op_name);
+        print_usage(opts, stderr());
+        return ExitCode::from(INVALID_USAGE);
+    }
+
+    if options.readonly && !READ_ONLY_OPERATIONS.contains(&op_name.as_str()) {
+        eprintln!("Readonly mode ({READONLY_ENV_VAR}=1): {op_name} will not modify any instances");
+    }
+
+    let org_accounts = matches.opt_present("org-accounts");
+    if !org_accounts && (matches.opt_present("org-confirm-accounts") || matches.opt_present("org-role")) {
+        return usage_error(opts, "--org-confirm-accounts and --org-role require --org-accounts");
+    }
+
+    let org_confirm_accounts = match matches.opt_str("org-confirm-accounts").map(|c| c.parse::<usize>()).transpose() {
+        Ok(count) => count,
+        Err(e) => return usage_error(opts, format!("Invalid --org-confirm-accounts count: {e}")),
+    };
+
+    // Given the blast radius, modifying instances across an organization requires the expected account count.
+    if org_accounts && org_confirm_accounts.is_none() && !READ_ONLY_OPERATIONS.contains(&op_name.as_str()) {
+        return usage_error(opts, format!("--org-accounts with {op_name} requires --org-confirm-accounts"));
+    }
+
+    let profile_regions = match parse_profile_region_map(&matches.opt_strs("profile-region-map")) {
+        Ok(profile_regions) => profile_regions,
+        Err(e) => return usage_error(opts, e),
+    };
+
+    // A profile selected by --profile or AWS_PROFILE implies its mapped region unless --region is given.
+    let profile = matches.opt_str("p").or_else(|| env::var("AWS_PROFILE").ok());
+    let region = matches.opt_str("r").or_else(|| profile.and_then(|profile| profile_regions.get(&profile).cloned()));
+
     let mut config = aws_config::from_env();
-    if let Some(region) = matches.opt_str("r") {
+    if let Some(region) = region {
         config = config.region(Region::new(region));
     }
 
@@ -60,26 +324,158 @@ async fn main() -> ExitCode {
     }
 
     let sdk_config = config.load().await;
-    let ec2_config = aws_sdk_ec2::config::Builder::from(&sdk_config).build();
-    let ec2 = aws_sdk_ec2::Client::from_conf(ec2_config);
 
-    let (op_name, op_args) = matches.free.split_first().unwrap();
-    let op_args = op_args.to_vec();
-
-    let result = match op_name.as_str() {
-        "print" => ops::print_instances::print_instances(ec2, op_args).await,
-        "reboot" => ops::reboot_instances(ec2, op_args).await,
-        "set-no-stop-before" => ops::set_no_stop::set_no_stop_before(ec2, op_args).await,
-        "start" => ops::start_instances(ec2, op_args).await,
-        "stop" => ops::stop_instances(ec2, op_args).await,
-        "terminate" => ops::terminate_instances(ec2, op_args).await,
-        _ => {
-            eprintln!("Unknown operation {}", op_name);
-            print_usage(opts, stderr());
-            return ExitCode::from(INVALID_USAGE);
+    if matches.opt_present("verbose") || matches.opt_present("verify-credentials") {
+        let identity = match CallerIdentity::fetch(&sdk_config).await {
+            Ok(identity) => identity,
+            Err(e) => return exit_code(opts, Err(e)),
+        };
+        let region = sdk_config.region().map(|region| region.as_ref()).unwrap_or("(no region)");
+        eprintln!("Acting as {} in {}", identity.arn, region);
+        options.audit_log.caller_arn = Some(identity.arn);
+    } else if options.audit_log.path.is_some() {
+        match CallerIdentity::fetch(&sdk_config).await {
+            Ok(identity) => options.audit_log.caller_arn = Some(identity.arn),
+            Err(e) => eprintln!("Warning: The audit log will not record the caller: {e}"),
         }
+    }
+
+    if matches.opt_present("stdin") || matches.opt_present("stdin-first") || matches.opt_present("stdin-last") {
+        let lines = match stdin().lines().collect::<Result<Vec<String>, _>>() {
+            Ok(lines) => lines,
+            Err(e) => {
+                eprintln!("Failed to read names from stdin: {e}");
+                return ExitCode::FAILURE;
+            }
+        };
+        let stdin_names =
+            lines.iter().map(|line| line.trim()).filter(|line| !line.is_empty()).map(String::from).collect();
+        op_args = merge_names(op_args, stdin_names, matches.opt_present("stdin-first"));
+    }
+
+    let restrict_to_permitted = matches.opt_present("check-permissions");
+    let result = if org_accounts {
+        let org_role = matches.opt_str("org-role").unwrap_or_else(|| DEFAULT_ORG_ROLE.to_string());
+        run_in_org_accounts(
+            &sdk_config,
+            options,
+            restrict_to_permitted,
+            &org_role,
+            org_confirm_accounts,
+            op_name,
+            op_args,
+        )
+        .await
+    } else {
+        run_operation(&sdk_config, options, restrict_to_permitted, op_name, op_args).await
     };
 
+    if let Some(timings) = timings {
+        timings.print();
+    }
+
+    let result = match reporter {
+        Some(reporter) => result.and(reporter.write()),
+        None => result,
+    };
+
+    exit_code(opts, result)
+}
+
+/// Run the operation with the credentials and region of the given configuration.
+async fn run_operation(
+    sdk_config: &SdkConfig,
+    mut options: FindOptions,
+    restrict_to_permitted: bool,
+    op_name: &str,
+    op_args: Vec<String>,
+) -> NResult {
+    options.credentials = CredentialRefresher::new(sdk_config.credentials_provider().cloned());
+    options.region = sdk_config.region().map(|region| region.to_string()).unwrap_or_default();
+    options.sdk_config = Some(sdk_config.clone());
+    let ec2_config = aws_sdk_ec2::config::Builder::from(sdk_config).build();
+    let ec2 = aws_sdk_ec2::Client::from_conf(ec2_config);
+
+    if restrict_to_permitted {
+        check_permissions(&ec2, &mut options).await?;
+    }
+
+    match op_name {
+        "associate-eip" => ops::eip::associate_eip(ec2, options, op_args).await,
+        "diagnose" => ops::diagnose::diagnose(ec2, options, op_args).await,
+        "disassociate-eip" => ops::eip::disassociate_eip(ec2, options, op_args).await,
+        "find" => ops::find::find(ec2, options, op_args).await,
+        "ips" => ops::print_ips::print_ips(ec2, options, op_args).await,
+        "print" => ops::print_instances::print_instances(ec2, sdk_config, options, op_args).await,
+        "reboot" => ops::reboot_instances(ec2, options, op_args).await,
+        "set-no-stop-before" => ops::set_no_stop::set_no_stop_before(ec2, options, op_args).await,
+        "set-user-data" => ops::user_data::set_user_data(ec2, options, op_args).await,
+        "start" => ops::start_instances(ec2, options, op_args).await,
+        "status" => ops::status::print_status(ec2, sdk_config, options, op_args).await,
+        "stop" => ops::stop_instances(ec2, options, op_args).await,
+        "tags" => ops::tags::print_tags(ec2, options, op_args).await,
+        "terminate" => ops::terminate_instances(ec2, options, op_args).await,
+        "unprotect" => ops::set_no_stop::unprotect(ec2, options, op_args).await,
+        _ => Err(Error::InvalidUsage(format!("Unknown operation {op_name}"))),
+    }
+}
+
+/// Run the operation concurrently in every active account in the organization, assuming the role in each.
+///
+/// Failures in one account do not stop the others; each is reported with its account, and the run fails if any account
+/// failed. If `confirm_accounts` is given, nothing runs unless the organization has exactly that many accounts.
+async fn run_in_org_accounts(
+    sdk_config: &SdkConfig,
+    options: FindOptions,
+    restrict_to_permitted: bool,
+    role_template: &str,
+    confirm_accounts: Option<usize>,
+    op_name: &str,
+    op_args: Vec<String>,
+) -> NResult {
+    let accounts = org::list_accounts(sdk_config).await?;
+    if let Some(expected) = confirm_accounts {
+        if accounts.len() != expected {
+            return Err(Error::OrgAccountCountMismatch {
+                expected,
+                account_ids: accounts.into_iter().map(|account| account.id).collect(),
+            });
+        }
+    }
+
+    eprintln!("Running {op_name} in {} account(s)", accounts.len());
+    let runs = accounts.iter().map(|account| {
+        let options = options.clone();
+        let op_args = op_args.clone();
+        async move {
+            let account_config = org::assume_role_config(sdk_config, &org::role_arn(role_template, &account.id))?;
+            run_operation(&account_config, options, restrict_to_permitted, op_name, op_args).await
+        }
+    });
+    let results = join_all(runs).await;
+
+    let mut failed_account_ids = Vec::new();
+    for (account, result) in accounts.iter().zip(results) {
+        match result {
+            Ok(()) => (),
+            // Usage errors come from the operation's arguments and are the same in every account.
+            Err(e @ (Error::ShowUsage | Error::InvalidUsage(_))) => return Err(e),
+            Err(e) => {
+                eprintln!("Account {} ({}): {e}", account.id, account.name);
+                failed_account_ids.push(account.id.clone());
+            }
+        }
+    }
+
+    if failed_account_ids.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::OrgAccountsFailed(failed_account_ids))
+    }
+}
+
+/// Report the result of an operation and convert it to the process exit code.
+fn exit_code(opts: Options, result: NResult) -> ExitCode {
     match result {
         Ok(()) => ExitCode::SUCCESS,
         Err(Error::ShowUsage) => {
@@ -93,11 +489,56 @@ async fn main() -> ExitCode {
         }
         Err(e) => {
             eprintln!("{e}");
-            ExitCode::FAILURE
+            exit_code_for(&e)
         }
     }
 }
 
+/// Merge names read from stdin with the operation's arguments, before or after them as requested.
+///
+/// Operations parse their arguments with floating free arguments, so names may be placed before or after any
+/// operation options.
+fn merge_names(args: Vec<String>, stdin_names: Vec<String>, stdin_first: bool) -> Vec<String> {
+    if stdin_first {
+        stdin_names.into_iter().chain(args).collect()
+    } else {
+        args.into_iter().chain(stdin_names).collect()
+    }
+}
+
+/// Parse `--profile-region-map` values, each a comma-separated list of `profile=region` entries, into a map from
+/// profile to region. Later entries for a profile replace earlier ones.
+fn parse_profile_region_map(values: &[String]) -> Result<BTreeMap<String, String>, String> {
+    let mut profile_regions = BTreeMap::new();
+    for entry in values.iter().flat_map(|value| value.split(',')).filter(|entry| !entry.is_empty()) {
+        match entry.split_once('=') {
+            Some((profile, region)) if !profile.is_empty() && !region.is_empty() => {
+                profile_regions.insert(profile.to_string(), region.to_string());
+            }
+            _ => return Err(format!("Invalid --profile-region-map entry: {entry}")),
+        }
+    }
+
+    Ok(profile_regions)
+}
+
+/// The ARN patterns matching an instance profile given by name or ARN. A name matches the profile at any path in any
+/// account.
+fn instance_profile_arn_patterns(profile: &str) -> Vec<String> {
+    if profile.starts_with("arn:") {
+        vec![profile.to_string()]
+    } else {
+        vec![format!("arn:*:iam::*:instance-profile/{profile}"), format!("arn:*:iam::*:instance-profile/*/{profile}")]
+    }
+}
+
+fn usage_error<D: Display>(opts: Options, msg: D) -> ExitCode {
+    let mut e = stderr();
+    writeln!(e, "{}", msg).unwrap();
+    print_usage(opts, e);
+    ExitCode::from(INVALID_USAGE)
+}
+
 fn print_usage<W: Write>(opts: Options, mut out: W) {
     let brief = "Usage: ec2-by-name [options] <operation> <instance-name>...";
     let usage = opts.usage(brief);
@@ -105,14 +546,154 @@ fn print_usage<W: Write>(opts: Options, mut out: W) {
 
     out.write_all(
         r#"Operations:
+    associate-eip --allocation-id <eipalloc-id> [--allow-reassociation] <name>...
+                           Associate an Elastic IP address with the instance
+    diagnose [--format json] <name>...
+                           Show how each name resolves: the DNS addresses, what
+                           each IP address lookup and the Name tag matched, and
+                           whether the result is ambiguous
+    disassociate-eip [--allocation-id <eipalloc-id>] <name>...
+                           Disassociate Elastic IP addresses from the instance
+    find [--tag <key>[=<value>]]... [--interruption-imminent]
+                           Print the ids of instances matching the scope
+                           options (--az, --iam-profile, --image-id,
+                           --key-name, --state, --tag, --tenancy, --vpc-id)
+                           and tags; with --interruption-imminent, only Spot
+                           Instances whose request AWS has marked for
+                           interruption (the two-minute notice)
     info <name>...         Print instance information
-    print <name>...        Print instance ids
-    reboot <name>...       Reboot instances
-    set-no-stop-before --time <time> | --duration <duration>
-                           Set the NoStopBefore tag to the time or duration
-    start <name>...        Start instances
-    stop <name>...         Stop instances
+    ips <instance-id>...   Print the IP addresses and DNS names of instances
+    print [--sort id|launch-time] [--reverse] [--preserve-order]
+          [--group-by vpc|subnet] [--shell [--shell-per-name]] [--print0]
+          [--terraform-import <address>] [--save-set <file>]
+          [--format json] [--all-regions]
+          [--changed-since <time>|<snapshot-file>]
+          [--stream [--concurrency <n>]] <name>...
+                           Print instance ids (in name order with
+                           --preserve-order), optionally grouped by VPC or
+                           subnet, as shell variable assignments,
+                           NUL-terminated for xargs -0, as Terraform import
+                           blocks, or as JSON grouped by region, and optionally
+                           saving them to a file for use with --from-set; with
+                           --all-regions, search every enabled region; with
+                           --changed-since, print only the instances launched
+                           since the time, or the additions (+), removals (-),
+                           and state changes (~) since the snapshot file was
+                           last written, then update it; with --stream, print
+                           ids one per line as each page of results arrives,
+                           looking up at most <n> (default 8) names at once
+    reboot [--force] <name>...
+                           Reboot instances; with --force, force-stop them
+                           (risking data loss), wait, and start them again
+    set-no-stop-before --time <time> | --duration <duration> |
+          --until eob|eow [--business-day-end <HH:MM>] [--timezone <zone>] |
+          --after-launch <duration> [--confirm-diff [--yes]]
+                           Set the NoStopBefore tag to the time, duration from
+                           now, end of the business day or week (Friday) in the
+                           time zone (default local), or duration after each
+                           instance's launch, optionally showing the changes
+                           and asking first; durations may be humantime (4h)
+                           or ISO 8601 (PT4H)
+    set-user-data --file <file> [--stop-first] <name>...
+                           Replace the user data of stopped instances; with
+                           --stop-first, stop running instances first and
+                           start them again afterwards
+    start [--expect-state <state>] [--expect-change] <name>...
+                           Start instances, optionally refusing unless all are
+                           in the given state (e.g. stopped); with
+                           --expect-change, fail if none changed state
+    status [--estimate-cost [--live-pricing]] <name>...
+                           Print the state and type of instances, optionally
+                           with an approximate hourly on-demand price from a
+                           bundled (possibly stale) table or the Price List API;
+                           with --watch <interval>, repeat until interrupted;
+                           with --spot-detail, show each Spot Instance's
+                           interruption behavior and whether it is marked for
+                           interruption
+    stop [--skip-protected] [--calendar <file>] [--expect-change] <name>...
+                           Stop instances, optionally skipping those protected
+                           by the NoStopBefore or DoNotStop tags; with
+                           --calendar, refuse to stop any instance during a
+                           blackout window in the JSON calendar, regardless of
+                           tags; with --expect-change, fail if none changed
+                           state
+    tags [--key <key>] <name>...
+                           Print each instance's tags as key=value, or only
+                           the value of one tag
     terminate <name>...    Terminate instances
+    unprotect <name>...    Set the NoStopBefore tag to the current time
+    version [--json]       Print version and build information
+
+With --profile-region-map (e.g. prod=us-east-1,eu=eu-west-1), selecting a
+profile with --profile or AWS_PROFILE also selects its region unless --region
+is given.
+
+Progress messages (what an operation is about to do or has skipped) go to
+standard error so that standard output holds only results; --progress-to stdout
+restores the old behavior of writing them to standard output.
+
+With --audit-log, stop, terminate, reboot, set-no-stop-before, unprotect, and
+the stop done by set-user-data --stop-first append a JSON line with the time,
+operation, instance ids, caller ARN, and result to the file; failing to write
+it only prints a warning.
+
+Names are resolved through DNS, falling back to the instance Name tag (compared
+case-insensitively unless --case-sensitive is given).
+Spot Fleet request ids (sfr- followed by a UUID) resolve to the fleet's active
+instances.
+MAC addresses (02:ab:cd:ef:01:23, 02-ab-cd-ef-01-23, or 02ab.cdef.0123) resolve
+to the instances with a network interface that has the address.
+Full instance ids (e.g. i-0123456789abcdef0) are described directly, ignoring
+the scope options. Truncated instance ids (e.g. i-0abc) that have no DNS answer
+resolve to the one instance whose id starts with them; it is an error if more
+than one matches.
+With --select, when several instances are resolved and standard output is a
+terminal, a menu asks which of them to act on.
+With --per-name, each name is resolved and operated on separately and
+concurrently; a name that fails does not stop the others. The outcome of each
+name is printed to standard error, and the run fails if any name failed.
+--select, the checks over the resolved instances (such as --confirm-count and
+--strict), and the reports still cover all of the names together.
+With --retry-on-empty, a name that matches no instances (as can happen right
+after a launch, while EC2 is eventually consistent) is looked up again every
+2 seconds until it matches or --retry-for (default 30s) elapses.
+With status --watch --serve-metrics <addr> (e.g. :9100 for every interface),
+an OpenMetrics endpoint at /metrics reports an ec2_instance_state gauge for
+each instance, labelled with instance_id, name (the name that found it), and
+state; it is 1 for the current state and 0 for the others, and refreshes each
+interval. This requires building with the metrics feature.
+With --warn-cross-vpc, an IP address that matches instances in more than one
+VPC (as private addresses in peered VPCs can) prints a warning listing the
+VPCs; with --strict as well, it is an error.
+Operations that modify instances warn about each instance found by more than
+one name; with --strict, any operation fails instead.
+With --dedupe-report, the instances found by more than one name are listed,
+with the names that found them, on standard error after the run instead of as
+a warning for each; standard output is unchanged.
+
+Options for associate-eip, disassociate-eip, reboot, set-no-stop-before,
+set-user-data, start, stop, terminate, and unprotect:
+    --only-if-tagged <key>[=<value>]
+                           Refuse to act unless every instance has the tag
+    --confirm-count <count>
+                           Refuse to act unless exactly this many instances
+                           are found
+    --from-set <file>      Act on the instances saved by print --save-set
+                           instead of resolving names; fails if any of them
+                           no longer exist
+    --all-regions          With --from-set, look for instances that are not in
+                           this region in every enabled region and report
+                           where each is
+
+With --org-accounts, the accounts are listed through AWS Organizations (using
+credentials from the management account or a delegated administrator) and the
+operation runs in all of them concurrently, assuming --org-role (default
+OrganizationAccountAccessRole) in each. Operations other than diagnose, find,
+ips, print, status, and tags also require --org-confirm-accounts with the number
+of accounts.
+
+If the EC2_BY_NAME_READONLY environment variable is set to 1, operations resolve
+instances and report what they would do without modifying anything.
 "#
         .as_bytes(),
     )
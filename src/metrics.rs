@@ -0,0 +1,114 @@
+use {
+    crate::{
+        ec2::ResolvedInstances,
+        error::{Error, Result},
+    },
+    hyper::{
+        header::CONTENT_TYPE,
+        service::{make_service_fn, service_fn},
+        Body, Method, Request, Response, Server, StatusCode,
+    },
+    std::{
+        convert::Infallible,
+        fmt::Write,
+        net::SocketAddr,
+        sync::{Arc, RwLock},
+    },
+};
+
+/// The content type of the OpenMetrics text exposition format.
+const OPENMETRICS_CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// The instance states reported for each instance; the gauge for the current state is 1 and the others are 0.
+const STATES: [&str; 6] = ["pending", "running", "shutting-down", "stopped", "stopping", "terminated"];
+
+/// Serves the most recent instance states at `/metrics` until the process exits.
+pub(crate) struct MetricsExporter {
+    body: Arc<RwLock<String>>,
+}
+
+impl MetricsExporter {
+    /// Start serving on the given address. Nothing is reported until the first `update`.
+    pub(crate) fn start(addr: SocketAddr) -> Result<Self> {
+        let body = Arc::new(RwLock::new(render(&ResolvedInstances::default())));
+        let served_body = body.clone();
+        let make_service = make_service_fn(move |_| {
+            let body = served_body.clone();
+            async move { Ok::<_, Infallible>(service_fn(move |request| respond(request, body.clone()))) }
+        });
+
+        let server = Server::try_bind(&addr)
+            .map_err(|e| Error::Runtime(format!("Failed to listen on {addr}: {e}")))?
+            .serve(make_service);
+        tokio::spawn(async move {
+            if let Err(e) = server.await {
+                eprintln!("Warning: Metrics server failed: {e}");
+            }
+        });
+
+        Ok(Self {
+            body,
+        })
+    }
+
+    /// Replace the reported states with those of the given instances.
+    pub(crate) fn update(&self, resolved: &ResolvedInstances) {
+        *self.body.write().expect("Metrics lock poisoned") = render(resolved);
+    }
+}
+
+async fn respond(request: Request<Body>, body: Arc<RwLock<String>>) -> std::result::Result<Response<Body>, Infallible> {
+    let response = if request.method() == Method::GET && request.uri().path() == "/metrics" {
+        let body = body.read().expect("Metrics lock poisoned").clone();
+        Response::builder().header(CONTENT_TYPE, OPENMETRICS_CONTENT_TYPE).body(Body::from(body))
+    } else {
+        Response::builder().status(StatusCode::NOT_FOUND).body(Body::empty())
+    };
+
+    Ok(response.expect("Failed to build metrics response"))
+}
+
+/// Parse a listen address, where `:port` listens on every interface.
+pub(crate) fn parse_listen_addr(addr: &str) -> Result<SocketAddr> {
+    let full_addr = match addr.strip_prefix(':') {
+        Some(port) => format!("0.0.0.0:{port}"),
+        None => addr.to_string(),
+    };
+
+    full_addr.parse().map_err(|_| Error::InvalidUsage(format!("Invalid listen address: {addr}")))
+}
+
+/// Render the instance states as an `ec2_instance_state` gauge family labelled by instance_id, name (the name that
+/// resolved to the instance), and state.
+fn render(resolved: &ResolvedInstances) -> String {
+    let mut body = String::new();
+    body.push_str("# TYPE ec2_instance_state gauge\n");
+    body.push_str("# HELP ec2_instance_state Whether the instance found by name is in the given state.\n");
+
+    for (name, instance_ids) in resolved.per_name.iter() {
+        for instance_id in instance_ids {
+            let Some(instance) = resolved.instances.get(instance_id) else {
+                continue;
+            };
+            let current = instance.state.as_ref().and_then(|state| state.name.as_ref()).map(|name| name.as_str());
+
+            for state in STATES {
+                let value = u8::from(current == Some(state));
+                let _ = writeln!(
+                    body,
+                    "ec2_instance_state{{instance_id=\"{}\",name=\"{}\",state=\"{state}\"}} {value}",
+                    escape_label(instance_id),
+                    escape_label(name)
+                );
+            }
+        }
+    }
+
+    body.push_str("# EOF\n");
+    body
+}
+
+/// Escape a label value as required by the exposition format.
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
@@ -1,63 +1,471 @@
+pub(crate) mod diagnose;
+pub(crate) mod eip;
+pub(crate) mod find;
 pub(crate) mod print_instances;
+pub(crate) mod print_ips;
 pub(crate) mod set_no_stop;
+pub(crate) mod status;
+pub(crate) mod tags;
+pub(crate) mod user_data;
+pub(crate) mod version;
 
 use {
-    crate::{ec2::find_instances_then, error::NResult},
+    crate::{
+        credentials::CredentialRefresher,
+        ec2::{describe_instances_by_id, find_instances_then, find_resolved_then, FindOptions},
+        error::{Error, NResult, Result},
+        guard::{is_stop_protected, MaintenanceCalendar},
+        instance_set::InstanceSet,
+        progress::Progress,
+        timings::Timings,
+    },
     aws_sdk_ec2::{
         self,
     },
     aws_sdk_ec2::{
-        model::{InstanceState, InstanceStateChange},
+        model::{Instance, InstanceState, InstanceStateChange, InstanceStateName},
+    },
+    chrono::Utc,
+    getopts::{Matches, Options},
+    log::debug,
+    std::{
+        fmt::{Display, Formatter, Result as FmtResult},
+        time::{Duration, Instant},
     },
+    tokio::time::sleep,
 };
 
-pub(crate) async fn reboot_instances(ec2: aws_sdk_ec2::Client, args: Vec<String>) -> NResult {
-    find_instances_then(ec2.clone(), args, |instance_ids| async move {
-        println!("Rebooting instances: {}", instance_ids.join(" "));
-        ec2.reboot_instances().set_instance_ids(Some(instance_ids.clone())).send().await?;
-        println!("Rebooted instances: {}", instance_ids.join(" "));
+/// How often to poll instance states while waiting for a state change.
+const STATE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long to wait for instances to reach a state before giving up.
+const STATE_WAIT_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// A tag that must be present on every instance before a mutating operation is allowed to proceed.
+#[derive(Clone, Debug)]
+pub(crate) struct RequiredTag {
+    key: String,
+    /// The required value; if `None`, any value is accepted.
+    value: Option<String>,
+}
+
+impl RequiredTag {
+    fn parse(s: &str) -> Result<Self> {
+        let (key, value) = match s.split_once('=') {
+            Some((key, value)) => (key, Some(value.to_string())),
+            None => (s, None),
+        };
+
+        if key.is_empty() {
+            return Err(Error::InvalidUsage(format!("Invalid tag specification: {s}")));
+        }
+
+        Ok(Self {
+            key: key.to_string(),
+            value,
+        })
+    }
+
+    fn is_present_on(&self, instance: &Instance) -> bool {
+        instance.tags.as_deref().unwrap_or_default().iter().any(|tag| {
+            tag.key.as_deref() == Some(self.key.as_str())
+                && (self.value.is_none() || tag.value.as_deref() == self.value.as_deref())
+        })
+    }
+}
+
+impl Display for RequiredTag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match &self.value {
+            Some(value) => write!(f, "{}={}", self.key, value),
+            None => write!(f, "{}", self.key),
+        }
+    }
+}
+
+/// Options common to all operations that modify instances.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct MutatingOptions {
+    required_tags: Vec<RequiredTag>,
+    readonly: bool,
+
+    /// Where progress messages are written.
+    pub(crate) progress: Progress,
+
+    /// Refreshes expired credentials for the calls made by the checks.
+    credentials: CredentialRefresher,
+}
+
+impl MutatingOptions {
+    /// Register the options common to mutating operations.
+    pub(crate) fn add_to(opts: &mut Options) {
+        opts.optmulti(
+            "",
+            "only-if-tagged",
+            "Refuse to act unless every instance has the specified tag",
+            "<key>[=<value>]",
+        );
+        opts.optopt("", "confirm-count", "Refuse to act unless exactly this many instances are found", "<count>");
+        opts.optopt("", "from-set", "Act on the instances saved by print --save-set instead of names", "<file>");
+        opts.optflag("", "all-regions", "With --from-set, report which region holds any instances not in this one");
+    }
+
+    /// Parse the mutating options, applying any that affect instance resolution to `options`.
+    pub(crate) fn from_matches(matches: &Matches, options: &mut FindOptions) -> Result<Self> {
+        let required_tags =
+            matches.opt_strs("only-if-tagged").iter().map(|s| RequiredTag::parse(s)).collect::<Result<Vec<_>>>()?;
+
+        if let Some(count) = matches.opt_str("confirm-count") {
+            let count = count.parse().map_err(|_| Error::InvalidUsage(format!("Invalid count: {count}")))?;
+            options.expected_count = Some(count);
+        }
+
+        if let Some(path) = matches.opt_str("from-set") {
+            if !matches.free.is_empty() {
+                return Err(Error::InvalidUsage("Names cannot be specified with --from-set".to_string()));
+            }
+
+            options.instance_set = Some(InstanceSet::read(&path)?);
+        }
+
+        if matches.opt_present("all-regions") {
+            if options.instance_set.is_none() {
+                return Err(Error::InvalidUsage("--all-regions requires --from-set".to_string()));
+            }
+
+            options.locate_set_in_all_regions = true;
+        }
+
+        options.warn_overlaps = true;
+
+        Ok(Self {
+            required_tags,
+            readonly: options.readonly,
+            progress: options.progress,
+            credentials: options.credentials.clone(),
+        })
+    }
+
+    /// In readonly mode, print what would have been done and return true so the caller skips the change.
+    pub(crate) fn skip_if_readonly(&self, action: &str, instance_ids: &[String]) -> bool {
+        if self.readonly {
+            self.progress.println(format_args!("Readonly mode; would {action} instances: {}", instance_ids.join(" ")));
+        }
+
+        self.readonly
+    }
+
+    /// Verify that the instances may be acted upon, aborting the whole batch if any may not.
+    pub(crate) async fn check(&self, ec2: &aws_sdk_ec2::Client, instance_ids: &[String]) -> NResult {
+        if self.required_tags.is_empty() {
+            return Ok(());
+        }
+
+        let instances = describe_instances_by_id(ec2.clone(), &self.credentials, instance_ids).await?;
+        for required_tag in &self.required_tags {
+            let mut offenders: Vec<String> = instances
+                .iter()
+                .filter(|instance| !required_tag.is_present_on(instance))
+                .filter_map(|instance| instance.instance_id.clone())
+                .collect();
+
+            if !offenders.is_empty() {
+                offenders.sort();
+                return Err(Error::MissingRequiredTag {
+                    tag: required_tag.to_string(),
+                    instance_ids: offenders,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Parse the arguments to a simple mutating operation, returning the options and the names to operate on.
+fn parse_mutating_args(options: &mut FindOptions, args: Vec<String>) -> Result<(MutatingOptions, Vec<String>)> {
+    let mut opts = Options::new();
+    MutatingOptions::add_to(&mut opts);
+    opts.optflag("h", "help", "Print this help menu");
+
+    let matches = opts.parse(args)?;
+    if matches.opt_present("h") {
+        return Err(Error::ShowUsage);
+    }
+
+    Ok((MutatingOptions::from_matches(&matches, options)?, matches.free))
+}
+
+pub(crate) async fn reboot_instances(ec2: aws_sdk_ec2::Client, mut options: FindOptions, args: Vec<String>) -> NResult {
+    let mut opts = Options::new();
+    MutatingOptions::add_to(&mut opts);
+    opts.optflag("", "force", "Force-stop the instances, wait for them to stop, then start them");
+    opts.optflag("h", "help", "Print this help menu");
+
+    let matches = opts.parse(args)?;
+    if matches.opt_present("h") {
+        return Err(Error::ShowUsage);
+    }
+
+    let mutating = MutatingOptions::from_matches(&matches, &mut options)?;
+    let force = matches.opt_present("force");
+    let timings = options.timings.clone();
+    let credentials = options.credentials.clone();
+    let audit_log = options.audit_log.clone();
+    find_instances_then(ec2.clone(), &options, matches.free, |instance_ids| async move {
+        mutating.check(&ec2, &instance_ids).await?;
+        if mutating.skip_if_readonly("reboot", &instance_ids) {
+            return Ok(());
+        }
+
+        if force {
+            let result = force_reboot(&ec2, &timings, &credentials, mutating.progress, instance_ids.clone()).await;
+            audit_log.record("reboot --force", &instance_ids, &result);
+            return result;
+        }
+
+        mutating.progress.println(format_args!("Rebooting instances: {}", instance_ids.join(" ")));
+        let request = ec2.reboot_instances().set_instance_ids(Some(instance_ids.clone()));
+        let result = timings.time_mutation(credentials.retry_on_expiry(|| request.clone().send())).await;
+        audit_log.record("reboot", &instance_ids, &result);
+        result?;
+        mutating.progress.println(format_args!("Rebooted instances: {}", instance_ids.join(" ")));
         Ok(())
     })
     .await
 }
 
-pub(crate) async fn start_instances(ec2: aws_sdk_ec2::Client, args: Vec<String>) -> NResult {
-    find_instances_then(ec2.clone(), args, |instance_ids| async move {
-        println!("Starting instances: {}", instance_ids.join(" "));
-        let output = ec2.start_instances().set_instance_ids(Some(instance_ids)).send().await?;
-        print_instance_state_changes(output.starting_instances);
-        Ok(())
+/// Force-stop the instances, wait for them to stop, then start them again, for instances too wedged to respond to a
+/// reboot.
+///
+/// A forced stop does not give the operating system a chance to flush file system caches or shut down cleanly, so data
+/// not yet written to disk is lost and the file systems may need to be repaired on the next boot.
+async fn force_reboot(
+    ec2: &aws_sdk_ec2::Client,
+    timings: &Timings,
+    credentials: &CredentialRefresher,
+    progress: Progress,
+    instance_ids: Vec<String>,
+) -> NResult {
+    progress.println(format_args!("Force-stopping instances: {}", instance_ids.join(" ")));
+    let request = ec2.stop_instances().set_instance_ids(Some(instance_ids.clone())).force(true);
+    let output = timings.time_mutation(credentials.retry_on_expiry(|| request.clone().send())).await?;
+    print_instance_state_changes(output.stopping_instances);
+
+    wait_for_state(ec2, credentials, &instance_ids, InstanceStateName::Stopped).await?;
+
+    progress.println(format_args!("Starting instances: {}", instance_ids.join(" ")));
+    let request = ec2.start_instances().set_instance_ids(Some(instance_ids));
+    let output = timings.time_mutation(credentials.retry_on_expiry(|| request.clone().send())).await?;
+    print_instance_state_changes(output.starting_instances);
+    Ok(())
+}
+
+/// Poll until every instance is in the given state, giving up after `STATE_WAIT_TIMEOUT`.
+pub(crate) async fn wait_for_state(
+    ec2: &aws_sdk_ec2::Client,
+    credentials: &CredentialRefresher,
+    instance_ids: &[String],
+    state: InstanceStateName,
+) -> NResult {
+    let deadline = Instant::now() + STATE_WAIT_TIMEOUT;
+    loop {
+        let instances = describe_instances_by_id(ec2.clone(), credentials, instance_ids).await?;
+        let pending: Vec<&str> = instances
+            .iter()
+            .filter(|instance| instance.state.as_ref().and_then(|s| s.name.as_ref()) != Some(&state))
+            .filter_map(|instance| instance.instance_id.as_deref())
+            .collect();
+
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        if Instant::now() >= deadline {
+            return Err(Error::Runtime(format!(
+                "Timed out waiting for instances to be {}: {}",
+                state.as_str(),
+                pending.join(" ")
+            )));
+        }
+
+        debug!("Waiting for instances to be {}: {}", state.as_str(), pending.join(" "));
+        sleep(STATE_POLL_INTERVAL).await;
+    }
+}
+
+pub(crate) async fn start_instances(ec2: aws_sdk_ec2::Client, mut options: FindOptions, args: Vec<String>) -> NResult {
+    let mut opts = Options::new();
+    MutatingOptions::add_to(&mut opts);
+    opts.optflag("", "expect-change", "Fail if no instance changed state because all were already running");
+    opts.optopt("", "expect-state", "Refuse to act unless every instance is in this state (e.g. stopped)", "<state>");
+    opts.optflag("h", "help", "Print this help menu");
+
+    let matches = opts.parse(args)?;
+    if matches.opt_present("h") {
+        return Err(Error::ShowUsage);
+    }
+
+    let mutating = MutatingOptions::from_matches(&matches, &mut options)?;
+    let expect_change = matches.opt_present("expect-change");
+    let expect_state = matches.opt_str("expect-state").map(|state| InstanceStateName::from(state.as_str()));
+    if let Some(InstanceStateName::Unknown(state)) = &expect_state {
+        return Err(Error::InvalidUsage(format!("Unknown instance state: {state}")));
+    }
+
+    let timings = options.timings.clone();
+    let credentials = options.credentials.clone();
+    find_resolved_then(ec2.clone(), &options, matches.free, |resolved| async move {
+        let instance_ids = resolved.instance_ids();
+        mutating.check(&ec2, &instance_ids).await?;
+
+        if let Some(expected) = expect_state {
+            let violators: Vec<(String, String)> = resolved
+                .instances
+                .iter()
+                .filter_map(|(instance_id, instance)| {
+                    let state = instance.state.as_ref().and_then(|state| state.name.as_ref());
+                    (state != Some(&expected))
+                        .then(|| (instance_id.clone(), state.map(|s| s.as_str()).unwrap_or("unknown").to_string()))
+                })
+                .collect();
+
+            if !violators.is_empty() {
+                return Err(Error::UnexpectedState {
+                    expected: expected.as_str().to_string(),
+                    instances: violators,
+                });
+            }
+        }
+
+        if mutating.skip_if_readonly("start", &instance_ids) {
+            return Ok(());
+        }
+
+        mutating.progress.println(format_args!("Starting instances: {}", instance_ids.join(" ")));
+        let request = ec2.start_instances().set_instance_ids(Some(instance_ids.clone()));
+        let output = timings.time_mutation(credentials.retry_on_expiry(|| request.clone().send())).await?;
+        let changed = print_instance_state_changes(output.starting_instances);
+        check_changed(expect_change, changed, instance_ids)
     })
     .await
 }
 
-pub(crate) async fn stop_instances(ec2: aws_sdk_ec2::Client, args: Vec<String>) -> NResult {
-    find_instances_then(ec2.clone(), args, |instance_ids| async move {
-        println!("Stopping instances: {}", instance_ids.join(" "));
-        let output = ec2.stop_instances().set_instance_ids(Some(instance_ids)).send().await?;
-        print_instance_state_changes(output.stopping_instances);
-        Ok(())
+pub(crate) async fn stop_instances(ec2: aws_sdk_ec2::Client, mut options: FindOptions, args: Vec<String>) -> NResult {
+    let mut opts = Options::new();
+    MutatingOptions::add_to(&mut opts);
+    opts.optflag("", "expect-change", "Fail if no instance changed state because all were already stopped");
+    opts.optflag("", "skip-protected", "Skip instances protected by the NoStopBefore or DoNotStop tags");
+    opts.optopt("", "calendar", "Refuse to stop instances during a blackout window in this JSON calendar", "<file>");
+    opts.optflag("h", "help", "Print this help menu");
+
+    let matches = opts.parse(args)?;
+    if matches.opt_present("h") {
+        return Err(Error::ShowUsage);
+    }
+
+    let mutating = MutatingOptions::from_matches(&matches, &mut options)?;
+    let skip_protected = matches.opt_present("skip-protected");
+    let expect_change = matches.opt_present("expect-change");
+    let calendar = matches.opt_str("calendar").map(|path| MaintenanceCalendar::read(&path)).transpose()?;
+    let timings = options.timings.clone();
+    let credentials = options.credentials.clone();
+    let audit_log = options.audit_log.clone();
+    find_resolved_then(ec2.clone(), &options, matches.free, |resolved| async move {
+        let mut instance_ids = resolved.instance_ids();
+        mutating.check(&ec2, &instance_ids).await?;
+
+        // The calendar applies to every instance, so it is checked before (and overrides) the per-instance tags.
+        if let Some(calendar) = calendar {
+            calendar.check_stop(Utc::now())?;
+        }
+
+        if skip_protected {
+            let now = Utc::now();
+            let protected: Vec<String> = resolved
+                .instances
+                .iter()
+                .filter(|(_, instance)| is_stop_protected(instance, now))
+                .map(|(instance_id, _)| instance_id.clone())
+                .collect();
+
+            if !protected.is_empty() {
+                mutating.progress.println(format_args!(
+                    "Skipping {} protected instance(s): {}",
+                    protected.len(),
+                    protected.join(" ")
+                ));
+                instance_ids.retain(|instance_id| !protected.contains(instance_id));
+            }
+
+            if instance_ids.is_empty() {
+                mutating.progress.println("No unprotected instances to stop");
+                return check_changed(expect_change, 0, resolved.instance_ids());
+            }
+        }
+
+        if mutating.skip_if_readonly("stop", &instance_ids) {
+            return Ok(());
+        }
+
+        mutating.progress.println(format_args!("Stopping instances: {}", instance_ids.join(" ")));
+        let request = ec2.stop_instances().set_instance_ids(Some(instance_ids.clone()));
+        let result = timings.time_mutation(credentials.retry_on_expiry(|| request.clone().send())).await;
+        audit_log.record("stop", &instance_ids, &result);
+        let changed = print_instance_state_changes(result?.stopping_instances);
+        check_changed(expect_change, changed, instance_ids)
     })
     .await
 }
 
-pub(crate) async fn terminate_instances(ec2: aws_sdk_ec2::Client, args: Vec<String>) -> NResult {
-    find_instances_then(ec2.clone(), args, |instance_ids| async move {
-        println!("Terminating instances: {}", instance_ids.join(" "));
-        let output = ec2.terminate_instances().set_instance_ids(Some(instance_ids)).send().await?;
-        print_instance_state_changes(output.terminating_instances);
+pub(crate) async fn terminate_instances(
+    ec2: aws_sdk_ec2::Client,
+    mut options: FindOptions,
+    args: Vec<String>,
+) -> NResult {
+    let (mutating, names) = parse_mutating_args(&mut options, args)?;
+    let timings = options.timings.clone();
+    let credentials = options.credentials.clone();
+    let audit_log = options.audit_log.clone();
+    find_instances_then(ec2.clone(), &options, names, |instance_ids| async move {
+        mutating.check(&ec2, &instance_ids).await?;
+        if mutating.skip_if_readonly("terminate", &instance_ids) {
+            return Ok(());
+        }
+
+        mutating.progress.println(format_args!("Terminating instances: {}", instance_ids.join(" ")));
+        let request = ec2.terminate_instances().set_instance_ids(Some(instance_ids.clone()));
+        let result = timings.time_mutation(credentials.retry_on_expiry(|| request.clone().send())).await;
+        audit_log.record("terminate", &instance_ids, &result);
+        print_instance_state_changes(result?.terminating_instances);
         Ok(())
     })
     .await
 }
 
-fn print_instance_state_changes(changes: Option<Vec<InstanceStateChange>>) {
+/// Print each instance's state change, returning the number of instances whose state actually changed.
+pub(crate) fn print_instance_state_changes(changes: Option<Vec<InstanceStateChange>>) -> usize {
+    let mut changed = 0;
     for change in changes.unwrap_or(vec![]) {
         let instance_id = change.instance_id.unwrap_or("".to_string());
         let previous_state = instance_state_to_string(change.previous_state);
         let current_state = instance_state_to_string(change.current_state);
         println!("{}: {} -> {}", instance_id, previous_state, current_state);
+        if previous_state != current_state {
+            changed += 1;
+        }
+    }
+
+    changed
+}
+
+/// With `--expect-change`, fail if no instance changed state, meaning all were already in the target state.
+fn check_changed(expect_change: bool, changed: usize, instance_ids: Vec<String>) -> NResult {
+    if expect_change && changed == 0 {
+        return Err(Error::NoStateChange(instance_ids));
     }
+
+    Ok(())
 }
 
 fn instance_state_to_string(instance_state: Option<InstanceState>) -> String {
@@ -0,0 +1,246 @@
+use {
+    crate::{
+        ec2::{
+            classify_name, find_instances, find_instances_by_name_tag, get_instances_by_filter, ip_lookups,
+            normalize_address, resolve_targets, FindOptions, InstanceMap, NameKind, ResolvedTarget,
+        },
+        error::{Error, NResult, Result},
+    },
+    aws_sdk_ec2::model::Filter as Ec2Filter,
+    futures::future::join_all,
+    getopts::Options,
+    serde::Serialize,
+};
+
+/// Everything learned while resolving one name, as printed by `diagnose`.
+#[derive(Debug, Serialize)]
+struct Diagnosis {
+    name: String,
+    kind: &'static str,
+
+    /// Whether host names were resolved by the resolver command rather than DNS.
+    resolver_command: bool,
+
+    /// The addresses DNS returned, for host names, or the addresses and instance ids the resolver command returned.
+    addresses: Vec<String>,
+
+    /// Why the DNS lookup or resolver command failed, if it did.
+    dns_error: Option<String>,
+
+    /// What each IP address lookup, or instance id from the resolver command, matched.
+    ip_lookups: Vec<IpLookupResult>,
+
+    /// The instances whose Name tag matches, for host names. These are only used when DNS finds no instances.
+    name_tag: Option<LookupResult>,
+
+    /// The instances the name resolves to, as any other operation would see them.
+    instance_ids: Vec<String>,
+
+    /// Why the name would fail to resolve, if it would.
+    error: Option<String>,
+
+    /// Whether the name resolves to more than one instance.
+    ambiguous: bool,
+
+    /// Likely mistakes, such as a Name tag shared by several instances.
+    warnings: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct IpLookupResult {
+    address: String,
+    lookup: &'static str,
+    #[serde(flatten)]
+    result: LookupResult,
+}
+
+#[derive(Debug, Serialize)]
+struct LookupResult {
+    instance_ids: Vec<String>,
+    error: Option<String>,
+}
+
+impl From<&Result<InstanceMap>> for LookupResult {
+    fn from(result: &Result<InstanceMap>) -> Self {
+        match result {
+            Ok(instances) => Self {
+                instance_ids: instances.keys().cloned().collect(),
+                error: None,
+            },
+            Err(e) => Self {
+                instance_ids: vec![],
+                error: Some(e.to_string()),
+            },
+        }
+    }
+}
+
+/// Report, for each name, what DNS returned, what each IP address lookup and the Name tag fallback matched, and
+/// whether the result is ambiguous.
+pub(crate) async fn diagnose(ec2: aws_sdk_ec2::Client, options: FindOptions, args: Vec<String>) -> NResult {
+    let mut opts = Options::new();
+    opts.optopt("", "format", "Print the diagnosis in the specified format (json)", "<format>");
+    opts.optflag("h", "help", "Print this help menu");
+
+    let matches = opts.parse(args)?;
+    if matches.opt_present("h") {
+        return Err(Error::ShowUsage);
+    }
+
+    let json = match matches.opt_str("format").as_deref() {
+        None => false,
+        Some("json") => true,
+        Some(format) => return Err(Error::InvalidUsage(format!("Unknown output format: {format}"))),
+    };
+
+    if matches.free.is_empty() {
+        return Err(Error::InvalidUsage("No names specified".to_string()));
+    }
+
+    let diagnoses = join_all(matches.free.into_iter().map(|name| diagnose_name(&ec2, &options, name))).await;
+    if json {
+        println!("{}", serde_json::to_string(&diagnoses).expect("Failed to serialize diagnosis"));
+    } else {
+        for diagnosis in diagnoses.iter() {
+            print_diagnosis(diagnosis);
+        }
+    }
+
+    Ok(())
+}
+
+async fn diagnose_name(ec2: &aws_sdk_ec2::Client, options: &FindOptions, name: String) -> Diagnosis {
+    let kind = classify_name(&name);
+    let mut diagnosis = Diagnosis {
+        name: name.clone(),
+        kind: match kind {
+            NameKind::Hostname => "hostname",
+            NameKind::InstanceId => "instance-id",
+            NameKind::MacAddress => "mac-address",
+            NameKind::PartialInstanceId => "partial-instance-id",
+            NameKind::SpotFleetRequest => "spot-fleet-request",
+        },
+        resolver_command: options.resolver_command.is_some(),
+        addresses: vec![],
+        dns_error: None,
+        ip_lookups: vec![],
+        name_tag: None,
+        instance_ids: vec![],
+        error: None,
+        ambiguous: false,
+        warnings: vec![],
+    };
+
+    // Only host names go through DNS and the Name tag; the other kinds have a single lookup.
+    if let NameKind::Hostname = kind {
+        match resolve_targets(options, &name).await {
+            Ok(targets) => {
+                for target in targets {
+                    match target {
+                        ResolvedTarget::Address(address) => {
+                            let address = normalize_address(address);
+                            diagnosis.addresses.push(address.to_string());
+                            let (labels, lookups): (Vec<_>, Vec<_>) =
+                                ip_lookups(ec2, options, address).into_iter().unzip();
+                            for (lookup, result) in labels.into_iter().zip(join_all(lookups).await) {
+                                diagnosis.ip_lookups.push(IpLookupResult {
+                                    address: address.to_string(),
+                                    lookup,
+                                    result: LookupResult::from(&result),
+                                });
+                            }
+                        }
+                        ResolvedTarget::InstanceId(instance_id) => {
+                            diagnosis.addresses.push(instance_id.clone());
+                            let filter = Ec2Filter::builder().name("instance-id").values(&instance_id).build();
+                            let result = get_instances_by_filter(ec2.clone(), options, filter).await;
+                            diagnosis.ip_lookups.push(IpLookupResult {
+                                address: instance_id,
+                                lookup: "instance-id",
+                                result: LookupResult::from(&result),
+                            });
+                        }
+                    }
+                }
+            }
+            Err(e) => diagnosis.dns_error = Some(e.to_string()),
+        }
+
+        let name_tag = LookupResult::from(&find_instances_by_name_tag(ec2.clone(), options, &name).await);
+        if name_tag.instance_ids.len() > 1 {
+            diagnosis.warnings.push(format!(
+                "The Name tag {name} is used by {} instances: {}",
+                name_tag.instance_ids.len(),
+                name_tag.instance_ids.join(" ")
+            ));
+        }
+
+        let mut dns_instance_ids: Vec<&String> =
+            diagnosis.ip_lookups.iter().flat_map(|lookup| &lookup.result.instance_ids).collect();
+        dns_instance_ids.sort();
+        dns_instance_ids.dedup();
+        if !dns_instance_ids.is_empty()
+            && !name_tag.instance_ids.is_empty()
+            && dns_instance_ids != name_tag.instance_ids.iter().collect::<Vec<_>>()
+        {
+            diagnosis
+                .warnings
+                .push("DNS and the Name tag match different instances; the DNS match is used".to_string());
+        }
+
+        diagnosis.name_tag = Some(name_tag);
+    }
+
+    match find_instances(ec2.clone(), options, name).await {
+        Ok(instances) => diagnosis.instance_ids = instances.into_keys().collect(),
+        Err(e) => diagnosis.error = Some(e.to_string()),
+    }
+
+    diagnosis.ambiguous = diagnosis.instance_ids.len() > 1;
+    diagnosis
+}
+
+fn print_diagnosis(diagnosis: &Diagnosis) {
+    println!("{} ({}):", diagnosis.name, diagnosis.kind);
+    if diagnosis.kind == "hostname" {
+        let source = if diagnosis.resolver_command {
+            "Resolver command"
+        } else {
+            "DNS"
+        };
+        match &diagnosis.dns_error {
+            Some(e) => println!("  {source}: {e}"),
+            None => println!("  {source}: {}", diagnosis.addresses.join(" ")),
+        }
+
+        for lookup in diagnosis.ip_lookups.iter() {
+            println!("    {} {}: {}", lookup.address, lookup.lookup, format_lookup(&lookup.result));
+        }
+
+        if let Some(name_tag) = &diagnosis.name_tag {
+            println!("  Name tag: {}", format_lookup(name_tag));
+        }
+    }
+
+    match &diagnosis.error {
+        Some(e) => println!("  Resolves to: error: {e}"),
+        None if diagnosis.instance_ids.is_empty() => println!("  Resolves to: (none)"),
+        None => println!("  Resolves to: {}", diagnosis.instance_ids.join(" ")),
+    }
+
+    if diagnosis.ambiguous {
+        println!("  Ambiguous: {} instances", diagnosis.instance_ids.len());
+    }
+
+    for warning in diagnosis.warnings.iter() {
+        println!("  Warning: {warning}");
+    }
+}
+
+fn format_lookup(result: &LookupResult) -> String {
+    match &result.error {
+        Some(e) => format!("error: {e}"),
+        None if result.instance_ids.is_empty() => "(none)".to_string(),
+        None => result.instance_ids.join(" "),
+    }
+}
@@ -0,0 +1,111 @@
+use {
+    crate::{
+        ec2::{find_instances_then, FindOptions},
+        error::{Error, NResult, Result},
+        ops::MutatingOptions,
+    },
+    aws_sdk_ec2::model::Filter as Ec2Filter,
+    getopts::{Matches, Options},
+};
+
+/// Parse the arguments to an Elastic IP operation, requiring the names to resolve to exactly one instance.
+fn parse_eip_args(
+    options: &mut FindOptions,
+    args: Vec<String>,
+    add_opts: impl FnOnce(&mut Options),
+) -> Result<(MutatingOptions, Matches)> {
+    let mut opts = Options::new();
+    add_opts(&mut opts);
+    MutatingOptions::add_to(&mut opts);
+    opts.optflag("h", "help", "Print this help menu");
+
+    let matches = opts.parse(args)?;
+    if matches.opt_present("h") {
+        return Err(Error::ShowUsage);
+    }
+
+    let mutating = MutatingOptions::from_matches(&matches, options)?;
+    match options.expected_count {
+        None | Some(1) => options.expected_count = Some(1),
+        Some(_) => return Err(Error::InvalidUsage("Elastic IP operations act on exactly one instance".to_string())),
+    }
+
+    Ok((mutating, matches))
+}
+
+/// Associate an Elastic IP address with the single instance a name resolves to.
+pub(crate) async fn associate_eip(ec2: aws_sdk_ec2::Client, mut options: FindOptions, args: Vec<String>) -> NResult {
+    let (mutating, matches) = parse_eip_args(&mut options, args, |opts| {
+        opts.optopt("", "allocation-id", "The allocation id of the Elastic IP address", "<eipalloc-id>");
+        opts.optflag("", "allow-reassociation", "Move the address if it is associated with another instance");
+    })?;
+
+    let allocation_id = match matches.opt_str("allocation-id") {
+        Some(allocation_id) => allocation_id,
+        None => return Err(Error::InvalidUsage("--allocation-id is required".to_string())),
+    };
+    let allow_reassociation = matches.opt_present("allow-reassociation");
+
+    let timings = options.timings.clone();
+    let credentials = options.credentials.clone();
+    find_instances_then(ec2.clone(), &options, matches.free, |instance_ids| async move {
+        mutating.check(&ec2, &instance_ids).await?;
+        if mutating.skip_if_readonly("associate an Elastic IP address with", &instance_ids) {
+            return Ok(());
+        }
+
+        let instance_id = &instance_ids[0];
+        let request = ec2
+            .associate_address()
+            .allocation_id(&allocation_id)
+            .instance_id(instance_id)
+            .allow_reassociation(allow_reassociation);
+        let output = timings.time_mutation(credentials.retry_on_expiry(|| request.clone().send())).await?;
+        println!("{}: associated {} ({})", instance_id, allocation_id, output.association_id.unwrap_or_default());
+        Ok(())
+    })
+    .await
+}
+
+/// Disassociate the Elastic IP addresses associated with the single instance a name resolves to.
+pub(crate) async fn disassociate_eip(ec2: aws_sdk_ec2::Client, mut options: FindOptions, args: Vec<String>) -> NResult {
+    let (mutating, matches) = parse_eip_args(&mut options, args, |opts| {
+        opts.optopt("", "allocation-id", "Only disassociate this Elastic IP address", "<eipalloc-id>");
+    })?;
+    let allocation_id = matches.opt_str("allocation-id");
+
+    let timings = options.timings.clone();
+    let credentials = options.credentials.clone();
+    find_instances_then(ec2.clone(), &options, matches.free, |instance_ids| async move {
+        mutating.check(&ec2, &instance_ids).await?;
+        if mutating.skip_if_readonly("disassociate Elastic IP addresses from", &instance_ids) {
+            return Ok(());
+        }
+
+        let instance_id = &instance_ids[0];
+        let mut request =
+            ec2.describe_addresses().filters(Ec2Filter::builder().name("instance-id").values(instance_id).build());
+        if let Some(allocation_id) = &allocation_id {
+            request = request.allocation_ids(allocation_id);
+        }
+
+        let addresses = credentials.retry_on_expiry(|| request.clone().send()).await?.addresses.unwrap_or_default();
+        if addresses.is_empty() {
+            return Err(Error::Runtime(format!("{instance_id} has no matching Elastic IP addresses")));
+        }
+
+        for address in addresses {
+            let association_id = match address.association_id {
+                Some(association_id) => association_id,
+                None => continue,
+            };
+
+            let request = ec2.disassociate_address().association_id(&association_id);
+            timings.time_mutation(credentials.retry_on_expiry(|| request.clone().send())).await?;
+            println!("{}: disassociated {}", instance_id, address.allocation_id.unwrap_or_default());
+        }
+
+        Ok(())
+    })
+    .await
+}
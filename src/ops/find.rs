@@ -0,0 +1,58 @@
+use {
+    crate::{
+        ec2::{describe_spot_requests, get_instances_in_scope, FindOptions},
+        error::{Error, NResult},
+    },
+    getopts::Options,
+};
+
+/// Print the instances matching the scope filters and tags alone, without resolving any names.
+pub(crate) async fn find(ec2: aws_sdk_ec2::Client, mut options: FindOptions, args: Vec<String>) -> NResult {
+    let mut opts = Options::new();
+    opts.optmulti("", "tag", "Only match instances with the specified tag (repeatable)", "<key>[=<value>]");
+    opts.optflag(
+        "",
+        "interruption-imminent",
+        "Only match Spot Instances that AWS has marked for interruption (within two minutes)",
+    );
+    opts.optflag("h", "help", "Print this help menu");
+
+    let matches = opts.parse(args)?;
+    if matches.opt_present("h") {
+        return Err(Error::ShowUsage);
+    }
+
+    if !matches.free.is_empty() {
+        return Err(Error::InvalidUsage("find does not take names".to_string()));
+    }
+
+    for tag in matches.opt_strs("tag") {
+        options.add_tag_filter(&tag)?;
+    }
+
+    let interruption_imminent = matches.opt_present("interruption-imminent");
+    if interruption_imminent {
+        options.add_base_filter("instance-lifecycle", vec!["spot".to_string()]);
+    }
+
+    // Without any filters, this would list every instance in the region.
+    if options.base_filters.is_empty() {
+        return Err(Error::InvalidUsage("find requires at least one scope filter or --tag".to_string()));
+    }
+
+    let mut instances = get_instances_in_scope(ec2.clone(), &options).await?;
+
+    // Interruption notices are only visible on the Spot Instance requests, so they are checked after the describe.
+    if interruption_imminent {
+        let request_ids = instances.values().filter_map(|instance| instance.spot_instance_request_id.clone()).collect();
+        let spot_details = describe_spot_requests(&ec2, &options, request_ids).await?;
+        instances.retain(|_, instance| {
+            let spot = instance.spot_instance_request_id.as_ref().and_then(|id| spot_details.get(id));
+            spot.is_some_and(|spot| spot.interruption_notice().is_some())
+        });
+    }
+
+    let instance_ids: Vec<&str> = instances.keys().map(String::as_str).collect();
+    println!("{}", instance_ids.join(" "));
+    Ok(())
+}
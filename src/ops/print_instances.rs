@@ -1,9 +1,612 @@
-use crate::{ec2::find_instances_then, error::Error};
+use {
+    crate::{
+        ec2::{
+            find_overlaps, find_resolved_in_all_regions, find_resolved_then, format_dedupe_report, stream_resolved,
+            FindOptions, ResolvedInstances, Streamed,
+        },
+        error::{Error, NResult, Result},
+        instance_set::InstanceSet,
+        snapshot::Snapshot,
+    },
+    aws_sdk_ec2::model::Instance,
+    aws_types::SdkConfig,
+    chrono::{DateTime, Utc},
+    futures::stream::StreamExt,
+    getopts::Options,
+    humantime::parse_rfc3339_weak,
+    serde::Serialize,
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        io::{stdout, Write},
+        path::PathBuf,
+        pin::pin,
+        str::FromStr,
+    },
+};
+
+/// The number of names looked up at once with `--stream` unless `--concurrency` is given.
+const DEFAULT_STREAM_CONCURRENCY: usize = 8;
+
+/// The field used to order printed instances.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum SortKey {
+    Id,
+    LaunchTime,
+}
+
+impl FromStr for SortKey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "id" => Ok(Self::Id),
+            "launch-time" => Ok(Self::LaunchTime),
+            _ => Err(Error::InvalidUsage(format!("Unknown sort field: {s}"))),
+        }
+    }
+}
+
+/// The field used to group printed instances.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum GroupBy {
+    Subnet,
+    Vpc,
+}
+
+impl FromStr for GroupBy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "subnet" => Ok(Self::Subnet),
+            "vpc" => Ok(Self::Vpc),
+            _ => Err(Error::InvalidUsage(format!("Unknown group field: {s}"))),
+        }
+    }
+}
+
+impl GroupBy {
+    /// The id of the group the instance belongs to.
+    fn group_of(self, instance: &Instance) -> &str {
+        let group = match self {
+            Self::Subnet => instance.subnet_id.as_deref(),
+            Self::Vpc => instance.vpc_id.as_deref(),
+        };
+
+        group.unwrap_or("(none)")
+    }
+}
+
+/// The document printed by `--format json`: the instances found in each region, omitting regions with none. For
+/// example, `{"regions": [{"region": "us-east-1", "instances": [{"instance_id": "i-0abc", "names": ["web"]}]}]}`.
+#[derive(Debug, Serialize)]
+struct JsonOutput {
+    regions: Vec<JsonRegion>,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonRegion {
+    region: String,
+    instances: Vec<JsonInstance>,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonInstance {
+    instance_id: String,
+
+    /// The names that resolved to the instance, in the order they were given.
+    names: Vec<String>,
+}
+
+/// The baseline for `--changed-since`.
+#[derive(Clone, Debug)]
+enum ChangedSince {
+    /// Report the instances launched after this time.
+    Time(DateTime<Utc>),
+
+    /// Report the changes since the snapshot saved in this file, then save a new snapshot to it.
+    Snapshot(PathBuf),
+}
+
+impl ChangedSince {
+    /// Parse the argument as a time if it looks like one, otherwise as a snapshot file.
+    fn parse(s: &str) -> Self {
+        match parse_rfc3339_weak(s) {
+            Ok(time) => Self::Time(time.into()),
+            Err(_) => Self::Snapshot(PathBuf::from(s)),
+        }
+    }
+}
+
+/// How the instances within a region are ordered.
+#[derive(Clone, Copy, Debug)]
+struct Ordering {
+    sort_key: SortKey,
+    reverse: bool,
+    preserve_order: bool,
+}
+
+impl Ordering {
+    /// The resolved instances in this order.
+    fn apply(self, resolved: &ResolvedInstances) -> Vec<Instance> {
+        let mut instances: Vec<Instance> = resolved.instances.values().cloned().collect();
+        sort_instances(&mut instances, self.sort_key, self.reverse);
+        if self.preserve_order {
+            order_by_name(&mut instances, &resolved.per_name);
+        }
+
+        instances
+    }
+}
+
+pub(crate) async fn print_instances(
+    ec2: aws_sdk_ec2::Client,
+    sdk_config: &SdkConfig,
+    options: FindOptions,
+    args: Vec<String>,
+) -> NResult {
+    let mut opts = Options::new();
+    opts.optopt("", "sort", "Sort instances by the specified field (id, launch-time)", "<field>");
+    opts.optflag("", "reverse", "Reverse the sort order");
+    opts.optflag("", "preserve-order", "Order instances by the position of the name they were resolved from");
+    opts.optopt("", "group-by", "Group instances under a heading for each VPC or subnet (vpc, subnet)", "<field>");
+    opts.optflag("", "shell", "Print a shell-evaluable INSTANCE_IDS assignment");
+    opts.optflag("", "shell-per-name", "With --shell, also print a <name>_IDS assignment for each name");
+    opts.optflag("", "print0", "Terminate each instance id with a NUL character, for use with xargs -0");
+    opts.optopt(
+        "",
+        "terraform-import",
+        "Print a Terraform import block for each instance, addressed by the template ({name}, {index})",
+        "<address>",
+    );
+    opts.optopt("", "save-set", "Save the resolved instance ids to a file for use with --from-set", "<file>");
+    opts.optflag("", "all-regions", "Search every region enabled for the account");
+    opts.optopt("", "format", "Print the instances in the specified format (json)", "<format>");
+    opts.optopt(
+        "",
+        "changed-since",
+        "Print only instances launched since the time, or changed since the snapshot file (which is then updated)",
+        "<time|file>",
+    );
+    opts.optflag("", "stream", "Print instance ids one per line as each name is resolved, in name order");
+    opts.optopt("", "concurrency", "With --stream, the maximum number of names looked up at once (default 8)", "<n>");
+    opts.optflag("h", "help", "Print this help menu");
+
+    let matches = opts.parse(args)?;
+    if matches.opt_present("h") {
+        return Err(Error::ShowUsage);
+    }
+
+    let sort_key = matches.opt_str("sort").map(|s| s.parse()).transpose()?.unwrap_or(SortKey::Id);
+    let reverse = matches.opt_present("reverse");
+    let preserve_order = matches.opt_present("preserve-order");
+    if preserve_order && (matches.opt_present("sort") || reverse) {
+        return Err(Error::InvalidUsage("--preserve-order cannot be used with --sort or --reverse".to_string()));
+    }
+    let save_set = matches.opt_str("save-set").map(PathBuf::from);
+    let shell_per_name = matches.opt_present("shell-per-name");
+    let shell = matches.opt_present("shell") || shell_per_name;
+    let print0 = matches.opt_present("print0");
+    let group_by: Option<GroupBy> = matches.opt_str("group-by").map(|s| s.parse()).transpose()?;
+    let terraform_import = matches.opt_str("terraform-import");
+    let json = match matches.opt_str("format").as_deref() {
+        None => false,
+        Some("json") => true,
+        Some(format) => return Err(Error::InvalidUsage(format!("Unknown output format: {format}"))),
+    };
+    let changed_since = matches.opt_str("changed-since").map(|s| ChangedSince::parse(&s));
+    let modes = [shell, print0, group_by.is_some(), terraform_import.is_some(), json, changed_since.is_some()];
+    if modes.iter().filter(|&&mode| mode).count() > 1 {
+        return Err(Error::InvalidUsage(
+            "Only one of --changed-since, --format, --group-by, --print0, --shell, and --terraform-import may be \
+             specified"
+                .to_string(),
+        ));
+    }
+
+    let concurrency = match matches.opt_str("concurrency") {
+        Some(_) if !matches.opt_present("stream") => {
+            return Err(Error::InvalidUsage("--concurrency requires --stream".to_string()));
+        }
+        Some(n) => match n.parse::<usize>() {
+            Ok(n) if n > 0 => n,
+            _ => return Err(Error::InvalidUsage(format!("Invalid concurrency: {n}"))),
+        },
+        None => DEFAULT_STREAM_CONCURRENCY,
+    };
+
+    if matches.opt_present("stream") {
+        let whole_set_options = ["all-regions", "preserve-order", "reverse", "save-set", "sort"];
+        if modes.iter().any(|&mode| mode) || whole_set_options.iter().any(|&option| matches.opt_present(option)) {
+            return Err(Error::InvalidUsage(
+                "--stream cannot be used with other output or ordering options".to_string(),
+            ));
+        }
+        if options.report.is_some() || options.select {
+            return Err(Error::InvalidUsage("--stream cannot be used with --report or --select".to_string()));
+        }
+        // These check the whole set of instances, but the instances are printed before the set is known.
+        if options.strict || options.expected_count.is_some() {
+            return Err(Error::InvalidUsage("--stream cannot be used with --strict or --confirm-count".to_string()));
+        }
+
+        return print_streamed(ec2, &options, matches.free, concurrency).await;
+    }
+
+    let ordering = Ordering {
+        sort_key,
+        reverse,
+        preserve_order,
+    };
+
+    if matches.opt_present("all-regions") {
+        if (modes.iter().any(|&mode| mode) && !json) || save_set.is_some() {
+            return Err(Error::InvalidUsage(
+                "--all-regions can only be used with the default output or --format json".to_string(),
+            ));
+        }
+
+        let regions = find_resolved_in_all_regions(sdk_config, ec2, &options, matches.free).await?;
+        if json {
+            print_json(&regions, ordering);
+        } else {
+            let instances = regions.iter().flat_map(|resolved| ordering.apply(resolved));
+            let instance_ids: Vec<String> = instances.filter_map(|instance| instance.instance_id).collect();
+            println!("{}", instance_ids.join(" "));
+        }
+
+        return Ok(());
+    }
+
+    find_resolved_then(ec2, &options, matches.free, |resolved| async move {
+        if let Some(path) = save_set {
+            InstanceSet::write(&path, &resolved.instance_ids())?;
+        }
+
+        if let Some(address_template) = terraform_import {
+            print_terraform_imports(&resolved.per_name, &address_template)?;
+            return Ok(());
+        }
+
+        if let Some(changed_since) = changed_since {
+            return print_changes(&resolved, changed_since);
+        }
+
+        if json {
+            let regions = if resolved.instances.is_empty() {
+                vec![]
+            } else {
+                vec![resolved]
+            };
+            print_json(&regions, ordering);
+            return Ok(());
+        }
+
+        let instances = ordering.apply(&resolved);
+        if let Some(group_by) = group_by {
+            print_grouped(&instances, group_by);
+            return Ok(());
+        }
+
+        let instance_ids: Vec<&str> = instances.iter().filter_map(|instance| instance.instance_id.as_deref()).collect();
+        if print0 {
+            // Like find -print0, terminate every id (rather than separating them) and omit the newline, so the output
+            // is empty when there are no instances and xargs -0 never sees a stray newline.
+            let mut out = stdout().lock();
+            for instance_id in instance_ids {
+                write!(out, "{instance_id}\0").map_err(|e| Error::Io("<stdout>".into(), e))?;
+            }
+            return out.flush().map_err(|e| Error::Io("<stdout>".into(), e));
+        }
+
+        if !shell {
+            println!("{}", instance_ids.join(" "));
+            return Ok(());
+        }
+
+        println!("INSTANCE_IDS=\"{}\"", instance_ids.join(" "));
+        if shell_per_name {
+            let identifiers = unique_identifiers(&resolved.per_name);
+            for (name, name_instance_ids) in &resolved.per_name {
+                // Keep the requested sort order within each name.
+                let name_instance_ids: Vec<&str> =
+                    instance_ids.iter().copied().filter(|id| name_instance_ids.iter().any(|n| n == id)).collect();
+                println!("{}_IDS=\"{}\"", identifiers[name.as_str()], name_instance_ids.join(" "));
+            }
+        }
 
-pub(crate) async fn print_instances(ec2: aws_sdk_ec2::Client, args: Vec<String>) -> Result<(), Error> {
-    find_instances_then(ec2, args, |instance_ids| async move {
-        println!("{}", instance_ids.join(" "));
         Ok(())
     })
     .await
 }
+
+/// Print the instance ids of each name, one per line, as each page of results arrives, once every earlier name has
+/// been resolved. An instance resolved from several names is printed only once.
+///
+/// Each page's ids are written before the next update is taken from the stream, so when stdout is a pipe to a slow
+/// consumer, blocking writes hold back further lookups. A name whose lookup fails partway may already have had some of
+/// its instances printed.
+async fn print_streamed(
+    ec2: aws_sdk_ec2::Client,
+    options: &FindOptions,
+    names: Vec<String>,
+    concurrency: usize,
+) -> NResult {
+    let mut printed = BTreeSet::new();
+    let mut per_name = Vec::new();
+    let mut updates = pin!(stream_resolved(ec2, options, names, concurrency));
+    while let Some((name, update)) = updates.next().await {
+        let instance_ids = match update {
+            Streamed::Page(instance_ids) => instance_ids,
+            Streamed::Done(Ok(instance_ids)) => {
+                if options.dedupe_report {
+                    per_name.push((name, Ok(instance_ids.clone())));
+                }
+                // Print anything found by a lookup that does not stream its pages.
+                instance_ids
+            }
+            Streamed::Done(Err(e)) if options.skip_unresolvable && e.is_resolve_failure() => {
+                eprintln!("Warning: Skipping {name}: {e}");
+                continue;
+            }
+            Streamed::Done(Err(e)) => return Err(e),
+        };
+
+        let mut out = stdout().lock();
+        for instance_id in instance_ids {
+            if !printed.contains(&instance_id) {
+                writeln!(out, "{instance_id}").map_err(|e| Error::Io("<stdout>".into(), e))?;
+                printed.insert(instance_id);
+            }
+        }
+        out.flush().map_err(|e| Error::Io("<stdout>".into(), e))?;
+    }
+
+    if options.dedupe_report {
+        eprint!("{}", format_dedupe_report(&find_overlaps(&per_name)));
+    }
+
+    Ok(())
+}
+
+/// Print the changes to the resolved instances since the baseline, one per line.
+fn print_changes(resolved: &ResolvedInstances, changed_since: ChangedSince) -> NResult {
+    let current = Snapshot::of(&resolved.instances);
+    let changes = match changed_since {
+        ChangedSince::Time(time) => {
+            // Only launch times are known for a bare time, so the baseline is every instance launched by then.
+            let baseline = Snapshot {
+                taken_at: time,
+                instances: current
+                    .instances
+                    .iter()
+                    .filter(|(_, instance)| instance.launch_time.is_none_or(|launch_time| launch_time <= time))
+                    .map(|(instance_id, instance)| (instance_id.clone(), instance.clone()))
+                    .collect(),
+            };
+            baseline.diff(&current)
+        }
+        ChangedSince::Snapshot(path) => {
+            let previous = Snapshot::read(&path)?.unwrap_or_default();
+            let changes = previous.diff(&current);
+            current.write(&path)?;
+            changes
+        }
+    };
+
+    for change in changes {
+        println!("{change}");
+    }
+
+    Ok(())
+}
+
+/// Print the instances found in each region as a JSON document.
+fn print_json(regions: &[ResolvedInstances], ordering: Ordering) {
+    let output = JsonOutput {
+        regions: regions
+            .iter()
+            .map(|resolved| JsonRegion {
+                region: resolved.region.clone(),
+                instances: ordering
+                    .apply(resolved)
+                    .into_iter()
+                    .filter_map(|instance| instance.instance_id)
+                    .map(|instance_id| JsonInstance {
+                        names: resolved
+                            .per_name
+                            .iter()
+                            .filter(|(_, instance_ids)| instance_ids.contains(&instance_id))
+                            .map(|(name, _)| name.clone())
+                            .collect(),
+                        instance_id,
+                    })
+                    .collect(),
+            })
+            .collect(),
+    };
+
+    println!("{}", serde_json::to_string(&output).expect("Failed to serialize instances"));
+}
+
+/// Print a Terraform import block for each instance, grouped by the name it was resolved from.
+fn print_terraform_imports(per_name: &[(String, Vec<String>)], address_template: &str) -> NResult {
+    for (address, instance_id) in terraform_imports(per_name, address_template)? {
+        println!("import {{\n  to = {address}\n  id = \"{instance_id}\"\n}}");
+    }
+
+    Ok(())
+}
+
+/// The address and instance id of each Terraform import. `{name}` in the address template is replaced by the name (as
+/// an identifier) and `{index}` by the instance's position within that name. An instance resolved from several names
+/// is imported only once, under the first.
+///
+/// It is an error for two instances to get the same address, as they do when a name resolves to several instances and
+/// the template has no `{index}`.
+fn terraform_imports<'a>(
+    per_name: &'a [(String, Vec<String>)],
+    address_template: &str,
+) -> Result<Vec<(String, &'a str)>> {
+    let identifiers = unique_identifiers(per_name);
+    let mut imports = Vec::new();
+    let mut imported = BTreeSet::new();
+    let mut addresses: BTreeMap<String, &str> = BTreeMap::new();
+    for (name, instance_ids) in per_name {
+        let identifier = &identifiers[name.as_str()];
+        for (index, instance_id) in instance_ids.iter().enumerate() {
+            if !imported.insert(instance_id) {
+                continue;
+            }
+
+            let address = address_template.replace("{name}", identifier).replace("{index}", &index.to_string());
+            if let Some(other_id) = addresses.insert(address.clone(), instance_id) {
+                return Err(Error::InvalidUsage(format!(
+                    "--terraform-import would import both {other_id} and {instance_id} to {address}; include {{index}} \
+                     (and {{name}}) in the address"
+                )));
+            }
+            imports.push((address, instance_id.as_str()));
+        }
+    }
+
+    Ok(imports)
+}
+
+/// Print instances under a heading for each group, with groups sorted by id. Instances keep their order within a group.
+fn print_grouped(instances: &[Instance], group_by: GroupBy) {
+    let mut groups: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for instance in instances {
+        if let Some(instance_id) = instance.instance_id.as_deref() {
+            groups.entry(group_by.group_of(instance)).or_default().push(instance_id);
+        }
+    }
+
+    for (group, instance_ids) in groups {
+        println!("{group}:");
+        for instance_id in instance_ids {
+            println!("  {instance_id}");
+        }
+    }
+}
+
+/// Order instances by the position of the first name each was resolved from. The sort is stable, so instances from the
+/// same name keep their existing (id) order.
+fn order_by_name(instances: &mut [Instance], per_name: &[(String, Vec<String>)]) {
+    let mut positions: BTreeMap<&str, usize> = BTreeMap::new();
+    for (position, (_, instance_ids)) in per_name.iter().enumerate() {
+        for instance_id in instance_ids {
+            positions.entry(instance_id.as_str()).or_insert(position);
+        }
+    }
+
+    instances.sort_by_key(|instance| instance.instance_id.as_deref().and_then(|id| positions.get(id).copied()));
+}
+
+/// Sort instances by the given key. Instances are already ordered by id, so ties keep id order.
+fn sort_instances(instances: &mut [Instance], sort_key: SortKey, reverse: bool) {
+    match sort_key {
+        SortKey::Id => instances.sort_by(|a, b| a.instance_id.cmp(&b.instance_id)),
+        SortKey::LaunchTime => instances.sort_by_key(|instance| {
+            instance.launch_time.map(|launch_time| (launch_time.secs(), launch_time.subsec_nanos()))
+        }),
+    }
+
+    if reverse {
+        instances.reverse();
+    }
+}
+
+/// Convert a name into a valid shell or Terraform identifier by replacing invalid characters with underscores.
+fn sanitize_identifier(name: &str) -> String {
+    let mut identifier = String::with_capacity(name.len() + 1);
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        identifier.push('_');
+    }
+
+    for c in name.chars() {
+        match c {
+            'A'..='Z' | 'a'..='z' | '0'..='9' => identifier.push(c),
+            _ => identifier.push('_'),
+        }
+    }
+
+    identifier
+}
+
+/// Give each distinct name its own identifier. A name whose identifier is already taken by an earlier name (`web.01`
+/// after `web-01`, say) gets the first free numeric suffix (`web_01_2`), with a warning saying so.
+fn unique_identifiers(per_name: &[(String, Vec<String>)]) -> BTreeMap<&str, String> {
+    // A suffixed identifier must not take the plain identifier of a later name either.
+    let plain: BTreeSet<String> = per_name.iter().map(|(name, _)| sanitize_identifier(name)).collect();
+    let mut used = BTreeSet::new();
+    let mut identifiers = BTreeMap::new();
+    for (name, _) in per_name {
+        if identifiers.contains_key(name.as_str()) {
+            continue;
+        }
+
+        let base = sanitize_identifier(name);
+        let mut identifier = base.clone();
+        let mut suffix = 1;
+        while used.contains(&identifier) || (suffix > 1 && plain.contains(&identifier)) {
+            suffix += 1;
+            identifier = format!("{base}_{suffix}");
+        }
+
+        if identifier != base {
+            eprintln!("Warning: {name} is written as {identifier}, since another name is also {base}");
+        }
+
+        used.insert(identifier.clone());
+        identifiers.insert(name.as_str(), identifier);
+    }
+
+    identifiers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{terraform_imports, unique_identifiers};
+
+    #[test]
+    fn test_unique_identifiers() {
+        let per_name = |names: &[&str]| names.iter().map(|name| (name.to_string(), vec![])).collect::<Vec<_>>();
+
+        let names = per_name(&["web-01", "web.01", "db", "web-01"]);
+        let identifiers = unique_identifiers(&names);
+        assert_eq!(identifiers["web-01"], "web_01");
+        assert_eq!(identifiers["web.01"], "web_01_2");
+        assert_eq!(identifiers["db"], "db");
+        assert_eq!(identifiers.len(), 3);
+
+        // A suffix never takes the identifier another name has as its own.
+        let names = per_name(&["a-b", "a.b", "a_b_2"]);
+        let identifiers = unique_identifiers(&names);
+        assert_eq!(identifiers["a.b"], "a_b_3");
+        assert_eq!(identifiers["a_b_2"], "a_b_2");
+    }
+
+    #[test]
+    fn test_terraform_imports() {
+        let per_name = vec![
+            ("web".to_string(), vec!["i-0aaa".to_string(), "i-0bbb".to_string()]),
+            ("db".to_string(), vec!["i-0aaa".to_string(), "i-0ccc".to_string()]),
+        ];
+
+        // An instance found by a second name is imported only under the first.
+        let imports = terraform_imports(&per_name, "aws_instance.{name}[{index}]").unwrap();
+        assert_eq!(
+            imports,
+            [
+                ("aws_instance.web[0]".to_string(), "i-0aaa"),
+                ("aws_instance.web[1]".to_string(), "i-0bbb"),
+                ("aws_instance.db[1]".to_string(), "i-0ccc"),
+            ]
+        );
+
+        // Without {index}, the two instances of web would share an address.
+        assert!(terraform_imports(&per_name, "aws_instance.{name}").is_err());
+        assert!(terraform_imports(&per_name[..1], "aws_instance.web[{index}]").is_ok());
+    }
+}
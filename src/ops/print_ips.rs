@@ -0,0 +1,84 @@
+use {
+    crate::{
+        ec2::{describe_instances_by_id, is_instance_id, FindOptions},
+        error::{Error, NResult},
+    },
+    aws_sdk_ec2::model::Instance,
+};
+
+pub(crate) async fn print_ips(ec2: aws_sdk_ec2::Client, options: FindOptions, args: Vec<String>) -> NResult {
+    if args.is_empty() {
+        return Err(Error::InvalidUsage("No instance ids specified".to_string()));
+    }
+
+    if let Some(arg) = args.iter().find(|arg| !is_instance_id(arg)) {
+        return Err(Error::InvalidUsage(format!("Not an instance id: {arg}")));
+    }
+
+    let mut instances = describe_instances_by_id(ec2, &options.credentials, &args).await?;
+    instances.sort_by(|a, b| a.instance_id.cmp(&b.instance_id));
+
+    for instance in &instances {
+        print_instance_ips(instance);
+    }
+
+    let missing: Vec<String> = args
+        .into_iter()
+        .filter(|arg| !instances.iter().any(|instance| instance.instance_id.as_deref() == Some(arg.as_str())))
+        .collect();
+    if !missing.is_empty() {
+        return Err(Error::UnknownInstanceIds(missing));
+    }
+
+    Ok(())
+}
+
+fn print_instance_ips(instance: &Instance) {
+    let mut private_ipv4 = Vec::new();
+    let mut public_ipv4 = Vec::new();
+    let mut ipv6 = Vec::new();
+    let mut private_dns = Vec::new();
+    let mut public_dns = Vec::new();
+
+    push_unique(&mut private_ipv4, instance.private_ip_address.as_deref());
+    push_unique(&mut public_ipv4, instance.public_ip_address.as_deref());
+    push_unique(&mut private_dns, instance.private_dns_name.as_deref());
+    push_unique(&mut public_dns, instance.public_dns_name.as_deref());
+
+    for netif in instance.network_interfaces.as_deref().unwrap_or_default() {
+        for private_ip in netif.private_ip_addresses.as_deref().unwrap_or_default() {
+            push_unique(&mut private_ipv4, private_ip.private_ip_address.as_deref());
+            push_unique(&mut private_dns, private_ip.private_dns_name.as_deref());
+            if let Some(association) = &private_ip.association {
+                push_unique(&mut public_ipv4, association.public_ip.as_deref());
+                push_unique(&mut public_dns, association.public_dns_name.as_deref());
+            }
+        }
+
+        for ipv6_address in netif.ipv6_addresses.as_deref().unwrap_or_default() {
+            push_unique(&mut ipv6, ipv6_address.ipv6_address.as_deref());
+        }
+    }
+
+    println!("{}:", instance.instance_id.as_deref().unwrap_or_default());
+    print_field("private-ipv4", &private_ipv4);
+    print_field("public-ipv4", &public_ipv4);
+    print_field("ipv6", &ipv6);
+    print_field("private-dns", &private_dns);
+    print_field("public-dns", &public_dns);
+}
+
+/// Add a value to the list if it is present, non-empty, and not already in the list.
+fn push_unique(values: &mut Vec<String>, value: Option<&str>) {
+    if let Some(value) = value {
+        if !value.is_empty() && !values.iter().any(|v| v == value) {
+            values.push(value.to_string());
+        }
+    }
+}
+
+fn print_field(label: &str, values: &[String]) {
+    if !values.is_empty() {
+        println!("    {}: {}", label, values.join(" "));
+    }
+}
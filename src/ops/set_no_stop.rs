@@ -1,49 +1,547 @@
 use {
     crate::{
-        ec2::find_instances_then,
-        error::{Error, NResult},
+        credentials::CredentialRefresher,
+        ec2::{find_instances_then, find_resolved_then, FindOptions, MAX_FILTER_VALUES},
+        error::{Error, NResult, Result},
+        ops::MutatingOptions,
+        timings::Timings,
     },
-    aws_sdk_ec2::{self, model::Tag},
-    chrono::{DateTime, Duration, Utc},
+    aws_sdk_ec2::{
+        self,
+        model::{Filter as Ec2Filter, Instance, Tag},
+    },
+    chrono::{DateTime, Datelike, Duration, FixedOffset, Local, NaiveDateTime, NaiveTime, TimeZone, Utc, Weekday},
+    chrono_tz::Tz,
+    futures::stream::StreamExt,
     getopts::Options,
     humantime::{parse_duration, parse_rfc3339_weak},
-    std::time::UNIX_EPOCH,
+    std::{
+        collections::BTreeMap,
+        io::{stdin, IsTerminal},
+        time::{Duration as StdDuration, SystemTime},
+    },
+    tokio::time::sleep,
 };
 
-pub(crate) async fn set_no_stop_before(ec2: aws_sdk_ec2::Client, args: Vec<String>) -> NResult {
+/// The maximum number of resources tagged by a single create_tags call.
+const MAX_TAG_RESOURCES: usize = 1000;
+
+/// The hour the business day ends, used by `--until` unless `--business-day-end` is given.
+const DEFAULT_BUSINESS_DAY_END_HOUR: u32 = 18;
+
+/// How long to wait before reading the tags back a second time, since tag writes are eventually consistent.
+const TAG_VERIFY_RETRY_DELAY: StdDuration = StdDuration::from_secs(2);
+
+pub(crate) async fn set_no_stop_before(
+    ec2: aws_sdk_ec2::Client,
+    mut options: FindOptions,
+    args: Vec<String>,
+) -> NResult {
     let mut opts = Options::new();
+    opts.optopt("a", "after-launch", "Duration after each instance's launch time for no-stop-before", "<duration>");
     opts.optopt("d", "duration", "Duration for no-stop-before", "<duration>");
     opts.optopt("t", "time", "Time for no-stop-before", "<time>");
+    opts.optopt("u", "until", "End of business day (eob) or week (eow) for no-stop-before", "<shortcut>");
+    opts.optopt("", "business-day-end", "With --until, the time the business day ends (default 18:00)", "<HH:MM>");
+    opts.optopt("", "timezone", "With --until, the time zone: local (default), UTC, an IANA name, or -05:00", "<zone>");
+    opts.optflag("", "confirm-diff", "Show the current and new NoStopBefore values and ask before applying them");
+    opts.optflag("y", "yes", "With --confirm-diff, apply the changes without asking");
     opts.optflag("h", "help", "Print this help menu");
+    MutatingOptions::add_to(&mut opts);
 
     let matches = opts.parse(args)?;
     if matches.opt_present("h") {
         return Err(Error::ShowUsage);
     }
 
+    let mutating = MutatingOptions::from_matches(&matches, &mut options)?;
+    let confirm = matches.opt_present("confirm-diff").then(|| ConfirmDiff {
+        yes: matches.opt_present("y"),
+    });
+    let timings = options.timings.clone();
+    let credentials = options.credentials.clone();
+    let audit_log = options.audit_log.clone();
+
     if matches.opt_present("d") && matches.opt_present("t") {
         eprintln!("Cannot specify both duration and time");
         return Err(Error::InvalidUsage("Cannot specify both duration and time".to_string()));
     }
 
-    let duration = if let Some(duration_str) = matches.opt_str("d") {
-        parse_duration(&duration_str)?
+    if matches.opt_present("u") && (matches.opt_present("d") || matches.opt_present("t")) {
+        return Err(Error::InvalidUsage("Cannot specify --until with duration or time".to_string()));
+    }
+
+    if !matches.opt_present("u") && (matches.opt_present("business-day-end") || matches.opt_present("timezone")) {
+        return Err(Error::InvalidUsage("--business-day-end and --timezone require --until".to_string()));
+    }
+
+    if let Some(after_launch_str) = matches.opt_str("a") {
+        if matches.opt_present("d") || matches.opt_present("t") || matches.opt_present("u") {
+            return Err(Error::InvalidUsage("Cannot specify --after-launch with duration, time, or until".to_string()));
+        }
+
+        let after_launch = Duration::from_std(parse_no_stop_duration(&after_launch_str)?)
+            .expect("Failed to convert system duration to Chrono duration");
+        return set_no_stop_after_launch(ec2, options, mutating, confirm, after_launch, matches.free).await;
+    }
+
+    let timestamp: DateTime<Utc> = if let Some(duration_str) = matches.opt_str("d") {
+        let duration = parse_no_stop_duration(&duration_str)?;
+        Utc::now() + Duration::from_std(duration).expect("Failed to convert system duration to Chrono duration")
     } else if let Some(time_str) = matches.opt_str("t") {
-        parse_rfc3339_weak(&time_str)?.duration_since(UNIX_EPOCH).expect("Time cannot be represented since Unix epoch")
+        parse_rfc3339_weak(&time_str)?.into()
+    } else if let Some(shortcut) = matches.opt_str("u") {
+        let day_end = match matches.opt_str("business-day-end") {
+            Some(day_end) => NaiveTime::parse_from_str(&day_end, "%H:%M")
+                .map_err(|_| Error::InvalidUsage(format!("Invalid business day end: {day_end}")))?,
+            None => NaiveTime::from_hms_opt(DEFAULT_BUSINESS_DAY_END_HOUR, 0, 0).expect("Invalid business day end"),
+        };
+        let zone = parse_timezone(matches.opt_str("timezone").as_deref().unwrap_or("local"))?;
+        zone.until(&shortcut, Utc::now(), day_end)?
     } else {
-        return Err(Error::InvalidUsage("Must specify either duration or time".to_string()));
+        return Err(Error::InvalidUsage("Must specify either duration, time, or until".to_string()));
     };
 
-    let duration = Duration::from_std(duration).expect("Failed to convert system duration to Chrono duration");
-    let timestamp: DateTime<Utc> = Utc::now() + duration;
-    let timestamp_str: String = timestamp.format("%Y-%m-%dT%H:%M:%SZ").to_string();
-    let tag = Tag::builder().key("NoStopBefore").value(timestamp_str.clone()).build();
+    let timestamp_str = format_timestamp(timestamp);
+
+    find_resolved_then(ec2.clone(), &options, matches.free, |resolved| async move {
+        let instance_ids = resolved.instance_ids();
+        mutating.check(&ec2, &instance_ids).await?;
+        if mutating.skip_if_readonly("set NoStopBefore on", &instance_ids) {
+            return Ok(());
+        }
+
+        if let Some(confirm) = confirm {
+            let changes = resolved.instances.iter().map(|(instance_id, instance)| {
+                (instance_id.as_str(), current_no_stop_before(instance), timestamp_str.as_str())
+            });
+            confirm.confirm(changes.collect())?;
+        }
+
+        mutating.progress.println(format_args!("Setting NoStopBefore for instances: {}", instance_ids.join(" ")));
+        let timestamps = instance_ids.iter().map(|instance_id| (instance_id.clone(), timestamp_str.clone())).collect();
+        let result = write_no_stop_before(&ec2, &timings, &credentials, &timestamps).await;
+        audit_log.record("set-no-stop-before", &instance_ids, &result);
+        result?;
+        mutating.progress.println(format_args!(
+            "Set NoStopBefore to {} for instances: {}",
+            timestamp_str,
+            instance_ids.join(" ")
+        ));
+        Ok(())
+    })
+    .await
+}
+
+/// Set the NoStopBefore tag on each instance to its timestamp (keyed by instance id), in batches of the instances that
+/// share a timestamp, then read the tags back and fail if any instance does not have its new value.
+async fn write_no_stop_before(
+    ec2: &aws_sdk_ec2::Client,
+    timings: &Timings,
+    credentials: &CredentialRefresher,
+    timestamps: &BTreeMap<String, String>,
+) -> NResult {
+    let mut instance_ids_by_timestamp: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for (instance_id, timestamp_str) in timestamps {
+        instance_ids_by_timestamp.entry(timestamp_str).or_default().push(instance_id.clone());
+    }
+
+    for (timestamp_str, instance_ids) in instance_ids_by_timestamp {
+        for batch in instance_ids.chunks(MAX_TAG_RESOURCES) {
+            let request = ec2.create_tags().set_resources(Some(batch.to_vec())).tags(no_stop_before_tag(timestamp_str));
+            timings.time_mutation(credentials.retry_on_expiry(|| request.clone().send())).await?;
+        }
+    }
+
+    let instance_ids: Vec<String> = timestamps.keys().cloned().collect();
+    let mut unwritten = unwritten_no_stop_before(ec2, credentials, &instance_ids, timestamps).await?;
+    if !unwritten.is_empty() {
+        sleep(TAG_VERIFY_RETRY_DELAY).await;
+        unwritten = unwritten_no_stop_before(ec2, credentials, &unwritten, timestamps).await?;
+    }
+
+    if !unwritten.is_empty() {
+        let unwritten: Vec<String> =
+            unwritten.iter().map(|instance_id| format!("{instance_id}={}", timestamps[instance_id])).collect();
+        return Err(Error::Runtime(format!("NoStopBefore was not set on instances: {}", unwritten.join(" "))));
+    }
+
+    Ok(())
+}
+
+/// Read the NoStopBefore tags back through describe_tags, returning the instances that do not have their expected
+/// value in `timestamps`.
+async fn unwritten_no_stop_before(
+    ec2: &aws_sdk_ec2::Client,
+    credentials: &CredentialRefresher,
+    instance_ids: &[String],
+    timestamps: &BTreeMap<String, String>,
+) -> Result<Vec<String>> {
+    let mut values = BTreeMap::new();
+    for chunk in instance_ids.chunks(MAX_FILTER_VALUES) {
+        let request = ec2
+            .describe_tags()
+            .filters(Ec2Filter::builder().name("resource-id").set_values(Some(chunk.to_vec())).build())
+            .filters(Ec2Filter::builder().name("key").values("NoStopBefore").build());
+        let tags = credentials
+            .retry_on_expiry(|| async {
+                let mut tags = Vec::new();
+                let mut stream = request.clone().into_paginator().send();
+                while let Some(describe_tags_result) = stream.next().await {
+                    tags.extend(describe_tags_result?.tags.unwrap_or_default());
+                }
+                Ok::<_, Error>(tags)
+            })
+            .await?;
+        for tag in tags {
+            if let (Some(resource_id), Some(value)) = (tag.resource_id, tag.value) {
+                values.insert(resource_id, value);
+            }
+        }
+    }
+
+    let unwritten = instance_ids
+        .iter()
+        .filter(|instance_id| values.get(*instance_id) != timestamps.get(*instance_id))
+        .cloned()
+        .collect();
+    Ok(unwritten)
+}
+
+/// Set the NoStopBefore tag on each instance to its own launch time plus a duration. Instances launched long enough ago
+/// get a timestamp in the past, leaving them unprotected.
+async fn set_no_stop_after_launch(
+    ec2: aws_sdk_ec2::Client,
+    options: FindOptions,
+    mutating: MutatingOptions,
+    confirm: Option<ConfirmDiff>,
+    after_launch: Duration,
+    names: Vec<String>,
+) -> NResult {
+    let timings = options.timings.clone();
+    let credentials = options.credentials.clone();
+    let audit_log = options.audit_log.clone();
+
+    find_resolved_then(ec2.clone(), &options, names, |resolved| async move {
+        let instance_ids = resolved.instance_ids();
+        mutating.check(&ec2, &instance_ids).await?;
+        if mutating.skip_if_readonly("set NoStopBefore on", &instance_ids) {
+            return Ok(());
+        }
+
+        let mut timestamps = BTreeMap::new();
+        for (instance_id, instance) in &resolved.instances {
+            let launch_time = match instance.launch_time.map(SystemTime::try_from) {
+                Some(Ok(launch_time)) => DateTime::<Utc>::from(launch_time),
+                _ => return Err(Error::Runtime(format!("{instance_id} has no launch time"))),
+            };
+            timestamps.insert(instance_id.clone(), format_timestamp(launch_time + after_launch));
+        }
+
+        if let Some(confirm) = confirm {
+            let changes = resolved.instances.iter().map(|(instance_id, instance)| {
+                (instance_id.as_str(), current_no_stop_before(instance), timestamps[instance_id].as_str())
+            });
+            confirm.confirm(changes.collect())?;
+        }
+
+        mutating.progress.println(format_args!("Setting NoStopBefore for instances: {}", instance_ids.join(" ")));
+        let result = write_no_stop_before(&ec2, &timings, &credentials, &timestamps).await;
+        audit_log.record("set-no-stop-before --after-launch", &instance_ids, &result);
+        result?;
+        for (instance_id, timestamp_str) in timestamps {
+            println!("{}: NoStopBefore={}", instance_id, timestamp_str);
+        }
+
+        Ok(())
+    })
+    .await
+}
+
+/// Set the NoStopBefore tag to the current time, making the instances stoppable immediately.
+pub(crate) async fn unprotect(ec2: aws_sdk_ec2::Client, mut options: FindOptions, args: Vec<String>) -> NResult {
+    let mut opts = Options::new();
+    opts.optflag("h", "help", "Print this help menu");
+    MutatingOptions::add_to(&mut opts);
+
+    let matches = opts.parse(args)?;
+    if matches.opt_present("h") {
+        return Err(Error::ShowUsage);
+    }
+
+    let mutating = MutatingOptions::from_matches(&matches, &mut options)?;
+    let timings = options.timings.clone();
+    let credentials = options.credentials.clone();
+    let audit_log = options.audit_log.clone();
+    let timestamp_str = format_timestamp(Utc::now());
 
-    find_instances_then(ec2.clone(), matches.free, |instance_ids| async move {
-        println!("Setting NoStopBefore for instances: {}", instance_ids.join(" "));
-        ec2.create_tags().set_resources(Some(instance_ids.clone())).tags(tag).send().await?;
-        println!("Set NoStopBefore to {} for instances: {}", timestamp_str, instance_ids.join(" "));
+    find_instances_then(ec2.clone(), &options, matches.free, |instance_ids| async move {
+        mutating.check(&ec2, &instance_ids).await?;
+        if mutating.skip_if_readonly("unprotect", &instance_ids) {
+            return Ok(());
+        }
+
+        mutating.progress.println(format_args!("Unprotecting instances: {}", instance_ids.join(" ")));
+        let timestamps = instance_ids.iter().map(|instance_id| (instance_id.clone(), timestamp_str.clone())).collect();
+        let result = write_no_stop_before(&ec2, &timings, &credentials, &timestamps).await;
+        audit_log.record("unprotect", &instance_ids, &result);
+        result?;
+        for instance_id in instance_ids {
+            println!("{}: NoStopBefore={}", instance_id, timestamp_str);
+        }
         Ok(())
     })
     .await
 }
+
+/// Shows the current and proposed NoStopBefore values and asks before they are applied.
+#[derive(Clone, Copy, Debug)]
+struct ConfirmDiff {
+    /// Apply the changes without asking.
+    yes: bool,
+}
+
+impl ConfirmDiff {
+    /// Print the changes as `(instance id, current value, new value)` and ask whether to apply them. Without `--yes`,
+    /// standard input must be a terminal to ask.
+    fn confirm(self, changes: Vec<(&str, Option<&str>, &str)>) -> NResult {
+        for (instance_id, current, new) in &changes {
+            println!("{}: {} -> {}", instance_id, current.unwrap_or("(none)"), new);
+        }
+
+        if self.yes {
+            return Ok(());
+        }
+
+        if !stdin().is_terminal() {
+            return Err(Error::InvalidUsage(
+                "--confirm-diff requires --yes when standard input is not a terminal".to_string(),
+            ));
+        }
+
+        eprint!("Apply to {} instances? [y/N] ", changes.len());
+        let mut answer = String::new();
+        stdin().read_line(&mut answer).map_err(|e| Error::Io("<stdin>".into(), e))?;
+        match answer.trim() {
+            "y" | "Y" | "yes" => Ok(()),
+            _ => Err(Error::Runtime("Aborted".to_string())),
+        }
+    }
+}
+
+/// Parse a duration given either in humantime format (`4h 30m`) or, if it starts with `P`, as an ISO 8601 duration
+/// (`PT4H30M`).
+fn parse_no_stop_duration(s: &str) -> Result<StdDuration> {
+    if s.starts_with('P') {
+        parse_iso8601_duration(s)
+    } else {
+        Ok(parse_duration(s)?)
+    }
+}
+
+/// Parse an ISO 8601 duration such as `P1D` or `PT4H30M`. Weeks, days, hours, minutes, and (possibly fractional)
+/// seconds are supported; years and months are rejected since their length depends on the calendar.
+fn parse_iso8601_duration(s: &str) -> Result<StdDuration> {
+    let invalid = || Error::InvalidUsage(format!("Invalid ISO 8601 duration: {s}"));
+    let rest = s.strip_prefix('P').ok_or_else(invalid)?;
+    let (date, time) = match rest.split_once('T') {
+        Some((date, time)) if !time.is_empty() => (date, time),
+        Some(_) => return Err(invalid()),
+        None if !rest.is_empty() => (rest, ""),
+        None => return Err(invalid()),
+    };
+
+    if date.contains(['Y', 'M']) {
+        return Err(Error::InvalidUsage(format!("ISO 8601 durations in years or months are not supported: {s}")));
+    }
+
+    let date_seconds = sum_duration_components(date, &[('W', 604_800.0), ('D', 86_400.0)]).ok_or_else(invalid)?;
+    let time_seconds = sum_duration_components(time, &[('H', 3_600.0), ('M', 60.0), ('S', 1.0)]).ok_or_else(invalid)?;
+    StdDuration::try_from_secs_f64(date_seconds + time_seconds).map_err(|_| invalid())
+}
+
+/// Sum the number-and-designator components of one part of an ISO 8601 duration, in seconds. The designators must
+/// appear in the order given by `units`, each at most once. Returns None if the part is malformed.
+fn sum_duration_components(part: &str, units: &[(char, f64)]) -> Option<f64> {
+    let mut seconds = 0.0;
+    let mut number = String::new();
+    let mut next_unit = 0;
+
+    for c in part.chars() {
+        if c.is_ascii_digit() || c == '.' || c == ',' {
+            number.push(c);
+            continue;
+        }
+
+        let offset = units[next_unit..].iter().position(|(designator, _)| *designator == c)?;
+        let (_, unit_seconds) = units[next_unit + offset];
+        // ISO 8601 allows a comma as the decimal separator.
+        let value: f64 = number.replace(',', ".").parse().ok()?;
+        seconds += value * unit_seconds;
+        number.clear();
+        next_unit += offset + 1;
+    }
+
+    number.is_empty().then_some(seconds)
+}
+
+/// The time zone the `--until` business day is measured in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum UntilZone {
+    /// The system's local time zone.
+    Local,
+
+    /// An IANA time zone such as `America/New_York`.
+    Named(Tz),
+
+    /// A fixed offset from UTC such as `-05:00`.
+    Fixed(FixedOffset),
+}
+
+impl UntilZone {
+    /// The time named by an `--until` shortcut in this time zone.
+    fn until(self, shortcut: &str, now: DateTime<Utc>, day_end: NaiveTime) -> Result<DateTime<Utc>> {
+        match self {
+            Self::Local => until_shortcut(shortcut, now, day_end, &Local),
+            Self::Named(tz) => until_shortcut(shortcut, now, day_end, &tz),
+            Self::Fixed(offset) => until_shortcut(shortcut, now, day_end, &offset),
+        }
+    }
+}
+
+/// Parse a `--timezone` value: `local`, `UTC`, an IANA name such as `America/New_York`, or a fixed offset such as
+/// `-05:00`.
+fn parse_timezone(zone: &str) -> Result<UntilZone> {
+    match zone {
+        "local" => Ok(UntilZone::Local),
+        "UTC" | "utc" | "Z" => Ok(UntilZone::Fixed(FixedOffset::east_opt(0).expect("Invalid UTC offset"))),
+        _ => DateTime::parse_from_str(&format!("2000-01-01T00:00:00{zone}"), "%Y-%m-%dT%H:%M:%S%:z")
+            .map(|datetime| UntilZone::Fixed(*datetime.offset()))
+            .or_else(|_| zone.parse().map(UntilZone::Named))
+            .map_err(|_| Error::InvalidUsage(format!("Invalid time zone: {zone}"))),
+    }
+}
+
+/// The time named by an `--until` shortcut, in the given time zone: `eob` is the end of today's business day (the next
+/// weekday's if it has passed or today is a weekend) and `eow` is the end of Friday's business day (next Friday's if it
+/// has passed). The zone's offset is looked up on each candidate day, so a daylight saving change in between is honored.
+fn until_shortcut<Z: TimeZone>(
+    shortcut: &str,
+    now: DateTime<Utc>,
+    day_end: NaiveTime,
+    zone: &Z,
+) -> Result<DateTime<Utc>> {
+    let today = now.with_timezone(zone).date_naive();
+    let (first_day, period_days) = match shortcut {
+        "eob" => (today, 1),
+        "eow" => {
+            let days_until_friday =
+                (Weekday::Fri.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()) % 7;
+            (today + Duration::days(days_until_friday.into()), 7)
+        }
+        _ => return Err(Error::InvalidUsage(format!("Unknown --until shortcut: {shortcut} (expected eob or eow)"))),
+    };
+
+    let mut day = first_day;
+    loop {
+        let end = local_to_utc(zone, day.and_time(day_end));
+        let weekend = matches!(day.weekday(), Weekday::Sat | Weekday::Sun);
+        if end > now && !weekend {
+            return Ok(end);
+        }
+
+        day += Duration::days(period_days);
+    }
+}
+
+/// Convert a local time in the given zone to UTC. A time repeated when clocks go back resolves to its first occurrence;
+/// a time skipped when clocks go forward resolves to the same wall-clock time an hour later.
+fn local_to_utc<Z: TimeZone>(zone: &Z, local: NaiveDateTime) -> DateTime<Utc> {
+    zone.from_local_datetime(&local)
+        .earliest()
+        .or_else(|| zone.from_local_datetime(&(local + Duration::hours(1))).earliest())
+        .expect("Time zone skips more than an hour")
+        .with_timezone(&Utc)
+}
+
+/// The current value of the NoStopBefore tag on an instance, if any.
+fn current_no_stop_before(instance: &Instance) -> Option<&str> {
+    instance.tags.as_deref().unwrap_or_default().iter().find_map(|tag| match tag.key.as_deref() {
+        Some("NoStopBefore") => tag.value.as_deref(),
+        _ => None,
+    })
+}
+
+/// Format a timestamp the way it is stored in the NoStopBefore tag.
+fn format_timestamp(timestamp: DateTime<Utc>) -> String {
+    timestamp.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+fn no_stop_before_tag(timestamp_str: &str) -> Tag {
+    Tag::builder().key("NoStopBefore").value(timestamp_str).build()
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{parse_iso8601_duration, parse_no_stop_duration, parse_timezone, until_shortcut, UntilZone},
+        chrono::{DateTime, FixedOffset, NaiveTime, Utc},
+        chrono_tz::Tz,
+        std::time::Duration,
+    };
+
+    #[test]
+    fn test_parse_iso8601_duration() {
+        assert_eq!(parse_iso8601_duration("PT4H").unwrap(), Duration::from_secs(4 * 3600));
+        assert_eq!(parse_iso8601_duration("P1D").unwrap(), Duration::from_secs(86_400));
+        assert_eq!(parse_iso8601_duration("P1W").unwrap(), Duration::from_secs(7 * 86_400));
+        assert_eq!(parse_iso8601_duration("P1DT2H30M").unwrap(), Duration::from_secs(86_400 + 2 * 3600 + 30 * 60));
+        assert_eq!(parse_iso8601_duration("PT1.5S").unwrap(), Duration::from_millis(1500));
+        assert_eq!(parse_iso8601_duration("PT0,5H").unwrap(), Duration::from_secs(1800));
+
+        for invalid in ["P", "PT", "P1", "PT4", "P1H", "PT1D", "PT30M4H", "PT4H4H", "P1Y", "P2M", "PTH", "PT-1H"] {
+            assert!(parse_iso8601_duration(invalid).is_err(), "{invalid} should be rejected");
+        }
+    }
+
+    #[test]
+    fn test_parse_no_stop_duration_accepts_both_formats() {
+        assert_eq!(parse_no_stop_duration("4h").unwrap(), Duration::from_secs(4 * 3600));
+        assert_eq!(parse_no_stop_duration("PT4H").unwrap(), Duration::from_secs(4 * 3600));
+    }
+
+    #[test]
+    fn test_until_shortcut() {
+        let utc = &FixedOffset::east_opt(0).unwrap();
+        let day_end = NaiveTime::from_hms_opt(18, 0, 0).unwrap();
+        let at = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+
+        // Wednesday morning: today's end of business, and Friday's.
+        let wednesday = at("2024-05-15T09:00:00Z");
+        assert_eq!(until_shortcut("eob", wednesday, day_end, utc).unwrap(), at("2024-05-15T18:00:00Z"));
+        assert_eq!(until_shortcut("eow", wednesday, day_end, utc).unwrap(), at("2024-05-17T18:00:00Z"));
+
+        // Once the business day or week has ended, the next one is used.
+        let friday_evening = at("2024-05-17T19:00:00Z");
+        assert_eq!(until_shortcut("eob", friday_evening, day_end, utc).unwrap(), at("2024-05-20T18:00:00Z"));
+        assert_eq!(until_shortcut("eow", friday_evening, day_end, utc).unwrap(), at("2024-05-24T18:00:00Z"));
+
+        // Weekends are not business days.
+        let saturday = at("2024-05-18T09:00:00Z");
+        assert_eq!(until_shortcut("eob", saturday, day_end, utc).unwrap(), at("2024-05-20T18:00:00Z"));
+
+        // The business day ends in the given time zone.
+        let eastern = parse_timezone("-05:00").unwrap();
+        assert_eq!(eastern.until("eob", wednesday, day_end).unwrap(), at("2024-05-15T23:00:00Z"));
+
+        // Named zones use the offset in effect on the day the business day ends: EDT before November 3, EST after.
+        let new_york = parse_timezone("America/New_York").unwrap();
+        assert_eq!(new_york, UntilZone::Named(Tz::America__New_York));
+        assert_eq!(new_york.until("eob", wednesday, day_end).unwrap(), at("2024-05-15T22:00:00Z"));
+        let friday_before_dst_end = at("2024-11-01T23:00:00Z");
+        assert_eq!(new_york.until("eow", friday_before_dst_end, day_end).unwrap(), at("2024-11-08T23:00:00Z"));
+
+        assert!(until_shortcut("eom", wednesday, day_end, utc).is_err());
+        assert!(parse_timezone("Mars/Olympus").is_err());
+    }
+}
@@ -0,0 +1,128 @@
+use {
+    crate::{
+        ec2::{describe_spot_requests, find_resolved_then, FindOptions},
+        error::{Error, NResult},
+        pricing::{bundled_hourly_price, live_hourly_price},
+    },
+    aws_types::SdkConfig,
+    getopts::Options,
+    humantime::parse_duration,
+    std::collections::HashMap,
+    tokio::time::sleep,
+};
+
+#[cfg(feature = "metrics")]
+use crate::metrics::{parse_listen_addr, MetricsExporter};
+
+pub(crate) async fn print_status(
+    ec2: aws_sdk_ec2::Client,
+    sdk_config: &SdkConfig,
+    options: FindOptions,
+    args: Vec<String>,
+) -> NResult {
+    let mut opts = Options::new();
+    opts.optflag("", "estimate-cost", "Annotate each instance with its approximate hourly on-demand price");
+    opts.optflag("", "live-pricing", "Get prices from the AWS Price List API instead of the bundled table");
+    opts.optopt("", "serve-metrics", "With --watch, serve instance states as OpenMetrics on this address", "<addr>");
+    opts.optflag(
+        "",
+        "spot-detail",
+        "Annotate Spot Instances with their interruption behavior and any pending interruption",
+    );
+    opts.optopt("", "watch", "Print the status again at this interval until interrupted", "<interval>");
+    opts.optflag("h", "help", "Print this help menu");
+
+    let matches = opts.parse(args)?;
+    if matches.opt_present("h") {
+        return Err(Error::ShowUsage);
+    }
+
+    let estimate_cost = matches.opt_present("estimate-cost");
+    let live_pricing = matches.opt_present("live-pricing");
+    let spot_detail = matches.opt_present("spot-detail");
+    if live_pricing && !estimate_cost {
+        return Err(Error::InvalidUsage("--live-pricing requires --estimate-cost".to_string()));
+    }
+
+    let watch = matches.opt_str("watch").map(|interval| parse_duration(&interval)).transpose()?;
+    let serve_metrics = matches.opt_str("serve-metrics");
+    if serve_metrics.is_some() && watch.is_none() {
+        return Err(Error::InvalidUsage("--serve-metrics requires --watch".to_string()));
+    }
+
+    #[cfg(feature = "metrics")]
+    let metrics = serve_metrics.map(|addr| MetricsExporter::start(parse_listen_addr(&addr)?)).transpose()?;
+    #[cfg(not(feature = "metrics"))]
+    if serve_metrics.is_some() {
+        return Err(Error::InvalidUsage("--serve-metrics requires building with the metrics feature".to_string()));
+    }
+
+    let region = sdk_config.region().map(|region| region.as_ref().to_string()).unwrap_or_default();
+
+    loop {
+        let region = region.as_str();
+        let (ec2, options) = (&ec2, &options);
+        #[cfg(feature = "metrics")]
+        let metrics = &metrics;
+        let result = find_resolved_then(ec2.clone(), options, matches.free.clone(), |resolved| async move {
+            #[cfg(feature = "metrics")]
+            if let Some(metrics) = metrics {
+                metrics.update(&resolved);
+            }
+
+            let spot_details = if spot_detail {
+                let request_ids = resolved
+                    .instances
+                    .values()
+                    .filter_map(|instance| instance.spot_instance_request_id.clone())
+                    .collect();
+                describe_spot_requests(ec2, options, request_ids).await?
+            } else {
+                HashMap::new()
+            };
+
+            for (instance_id, instance) in resolved.instances {
+                let state = instance.state.and_then(|state| state.name).map(|name| name.as_str().to_string());
+                let instance_type = instance.instance_type.map(|t| t.as_str().to_string()).unwrap_or_default();
+                let mut line = format!("{}: {} {}", instance_id, state.unwrap_or_default(), instance_type);
+
+                if estimate_cost {
+                    let price = if live_pricing {
+                        live_hourly_price(sdk_config, region, &instance_type).await
+                    } else {
+                        bundled_hourly_price(region, &instance_type)
+                    };
+
+                    if let Some(price) = price {
+                        line.push_str(&format!(" ~${price:.4}/hr"));
+                    }
+                }
+
+                let spot = instance.spot_instance_request_id.as_ref().and_then(|id| spot_details.get(id));
+                if let Some(spot) = spot {
+                    line.push_str(&format!(" [spot, interruption: {}", spot.interruption_behavior));
+                    if let Some(code) = spot.interruption_notice() {
+                        line.push_str(&format!(", MARKED FOR INTERRUPTION: {code}"));
+                    }
+                    line.push(']');
+                }
+
+                println!("{line}");
+            }
+
+            Ok(())
+        })
+        .await;
+
+        // While watching, a failed refresh is reported and the next one is tried.
+        let Some(interval) = watch else {
+            return result;
+        };
+        if let Err(e) = result {
+            eprintln!("Warning: {e}");
+        }
+
+        sleep(interval).await;
+        println!();
+    }
+}
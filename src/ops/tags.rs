@@ -0,0 +1,48 @@
+use {
+    crate::{
+        ec2::{find_resolved_then, FindOptions},
+        error::{Error, NResult},
+    },
+    getopts::Options,
+};
+
+/// Print the tags of each resolved instance, or the value of a single tag.
+pub(crate) async fn print_tags(ec2: aws_sdk_ec2::Client, options: FindOptions, args: Vec<String>) -> NResult {
+    let mut opts = Options::new();
+    opts.optopt("", "key", "Print only the value of this tag, one line per instance", "<key>");
+    opts.optflag("h", "help", "Print this help menu");
+
+    let matches = opts.parse(args)?;
+    if matches.opt_present("h") {
+        return Err(Error::ShowUsage);
+    }
+
+    let key = matches.opt_str("key");
+
+    find_resolved_then(ec2, &options, matches.free, |resolved| async move {
+        for (instance_id, instance) in resolved.instances {
+            let mut tags: Vec<(String, String)> = instance
+                .tags
+                .unwrap_or_default()
+                .into_iter()
+                .map(|tag| (tag.key.unwrap_or_default(), tag.value.unwrap_or_default()))
+                .collect();
+            tags.sort();
+
+            match &key {
+                Some(key) => match tags.into_iter().find(|(tag_key, _)| tag_key == key) {
+                    Some((_, value)) => println!("{value}"),
+                    None => eprintln!("Warning: {instance_id} has no {key} tag"),
+                },
+                None => {
+                    for (tag_key, value) in tags {
+                        println!("{instance_id}: {tag_key}={value}");
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    })
+    .await
+}
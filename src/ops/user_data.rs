@@ -0,0 +1,136 @@
+use {
+    crate::{
+        ec2::{describe_instances_by_id, find_instances_then, FindOptions},
+        error::{Error, NResult},
+        ops::{print_instance_state_changes, wait_for_state, MutatingOptions},
+    },
+    aws_sdk_ec2::{
+        model::{BlobAttributeValue, InstanceStateName},
+        types::Blob,
+    },
+    getopts::Options,
+    std::fs,
+};
+
+/// The maximum size of user data, after base64 encoding.
+const MAX_USER_DATA_SIZE: usize = 16 * 1024;
+
+/// The size of data once base64-encoded, with padding.
+fn base64_len(len: usize) -> usize {
+    len.div_ceil(3) * 4
+}
+
+/// Replace the user data of stopped instances with the contents of a file.
+pub(crate) async fn set_user_data(ec2: aws_sdk_ec2::Client, mut options: FindOptions, args: Vec<String>) -> NResult {
+    let mut opts = Options::new();
+    opts.optopt("f", "file", "File containing the new user data", "<file>");
+    opts.optflag(
+        "",
+        "stop-first",
+        "Stop running instances and wait for them to stop before changing the user data, then start them again",
+    );
+    opts.optflag("h", "help", "Print this help menu");
+    MutatingOptions::add_to(&mut opts);
+
+    let matches = opts.parse(args)?;
+    if matches.opt_present("h") {
+        return Err(Error::ShowUsage);
+    }
+
+    let mutating = MutatingOptions::from_matches(&matches, &mut options)?;
+    let stop_first = matches.opt_present("stop-first");
+    let path = match matches.opt_str("f") {
+        Some(path) => path,
+        None => return Err(Error::InvalidUsage("--file is required".to_string())),
+    };
+
+    let user_data = fs::read(&path).map_err(|e| Error::Io(path.clone().into(), e))?;
+    let encoded_len = base64_len(user_data.len());
+    if encoded_len > MAX_USER_DATA_SIZE {
+        return Err(Error::InvalidUsage(format!(
+            "{path} is {encoded_len} bytes when base64-encoded; user data is limited to {MAX_USER_DATA_SIZE} bytes"
+        )));
+    }
+
+    let timings = options.timings.clone();
+    let credentials = options.credentials.clone();
+    let audit_log = options.audit_log.clone();
+    find_instances_then(ec2.clone(), &options, matches.free, |instance_ids| async move {
+        mutating.check(&ec2, &instance_ids).await?;
+        if mutating.skip_if_readonly("replace the user data of", &instance_ids) {
+            return Ok(());
+        }
+
+        // EC2 only allows the user data of stopped instances to be changed.
+        let instances = describe_instances_by_id(ec2.clone(), &credentials, &instance_ids).await?;
+        let state_of = |state: InstanceStateName| {
+            instances
+                .iter()
+                .filter(|instance| instance.state.as_ref().and_then(|s| s.name.as_ref()) == Some(&state))
+                .filter_map(|instance| instance.instance_id.clone())
+                .collect::<Vec<String>>()
+        };
+        let stopped = state_of(InstanceStateName::Stopped);
+        let not_stopped: Vec<String> = instance_ids.iter().filter(|id| !stopped.contains(id)).cloned().collect();
+
+        // Instances that were running (or starting) are started again afterwards; any that were already on their way
+        // down are left stopped.
+        let mut restart = state_of(InstanceStateName::Running);
+        restart.extend(state_of(InstanceStateName::Pending));
+
+        if !not_stopped.is_empty() {
+            if !stop_first {
+                return Err(Error::InstancesNotStopped(not_stopped));
+            }
+
+            mutating.progress.println(format_args!("Stopping instances: {}", not_stopped.join(" ")));
+            let request = ec2.stop_instances().set_instance_ids(Some(not_stopped.clone()));
+            let result = timings.time_mutation(credentials.retry_on_expiry(|| request.clone().send())).await;
+            audit_log.record("set-user-data --stop-first", &not_stopped, &result);
+            result?;
+            wait_for_state(&ec2, &credentials, &not_stopped, InstanceStateName::Stopped).await?;
+        }
+
+        let mut result = Ok(());
+        for instance_id in &instance_ids {
+            // The SDK base64-encodes blob attributes itself, so the raw file contents are sent here.
+            let request = ec2
+                .modify_instance_attribute()
+                .instance_id(instance_id)
+                .user_data(BlobAttributeValue::builder().value(Blob::new(user_data.clone())).build());
+            if let Err(e) = timings.time_mutation(credentials.retry_on_expiry(|| request.clone().send())).await {
+                result = Err(e);
+                break;
+            }
+            println!("{}: set user data from {}", instance_id, path);
+        }
+
+        // The instances this stopped are started again even if the user data could not be changed.
+        if !restart.is_empty() {
+            mutating.progress.println(format_args!("Starting instances: {}", restart.join(" ")));
+            let request = ec2.start_instances().set_instance_ids(Some(restart));
+            let output = timings.time_mutation(credentials.retry_on_expiry(|| request.clone().send())).await?;
+            print_instance_state_changes(output.starting_instances);
+        }
+
+        result
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{base64_len, MAX_USER_DATA_SIZE};
+
+    #[test]
+    fn test_base64_len() {
+        assert_eq!(base64_len(0), 0);
+        assert_eq!(base64_len(1), 4);
+        assert_eq!(base64_len(3), 4);
+        assert_eq!(base64_len(4), 8);
+
+        // 12 KiB is the most raw data that fits in the limit once encoded.
+        assert_eq!(base64_len(12 * 1024), MAX_USER_DATA_SIZE);
+        assert!(base64_len(12 * 1024 + 1) > MAX_USER_DATA_SIZE);
+    }
+}
@@ -0,0 +1,42 @@
+use {
+    crate::error::{Error, NResult},
+    getopts::Options,
+    serde::Serialize,
+};
+
+#[derive(Debug, Serialize)]
+struct VersionInfo {
+    version: &'static str,
+    git_sha: &'static str,
+    rustc_version: &'static str,
+    aws_sdk_ec2_version: &'static str,
+}
+
+const VERSION_INFO: VersionInfo = VersionInfo {
+    version: env!("CARGO_PKG_VERSION"),
+    git_sha: env!("EC2_BY_NAME_GIT_SHA"),
+    rustc_version: env!("EC2_BY_NAME_RUSTC_VERSION"),
+    aws_sdk_ec2_version: env!("EC2_BY_NAME_AWS_SDK_EC2_VERSION"),
+};
+
+pub(crate) fn print_version(args: Vec<String>) -> NResult {
+    let mut opts = Options::new();
+    opts.optflag("", "json", "Print the version information as JSON");
+    opts.optflag("h", "help", "Print this help menu");
+
+    let matches = opts.parse(args)?;
+    if matches.opt_present("h") {
+        return Err(Error::ShowUsage);
+    }
+
+    if matches.opt_present("json") {
+        println!("{}", serde_json::to_string(&VERSION_INFO).expect("Failed to serialize version information"));
+    } else {
+        println!("ec2-by-name {}", VERSION_INFO.version);
+        println!("git SHA: {}", VERSION_INFO.git_sha);
+        println!("rustc: {}", VERSION_INFO.rustc_version);
+        println!("aws-sdk-ec2: {}", VERSION_INFO.aws_sdk_ec2_version);
+    }
+
+    Ok(())
+}
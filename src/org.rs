@@ -0,0 +1,78 @@
+use {
+    crate::error::{Error, Result},
+    aws_config::sts::AssumeRoleProvider,
+    aws_sdk_organizations::model::AccountStatus,
+    aws_types::{credentials::SharedCredentialsProvider, SdkConfig},
+    futures::stream::StreamExt,
+    log::debug,
+};
+
+/// The session name used when assuming the role in each member account, so the sessions are identifiable in
+/// CloudTrail.
+const SESSION_NAME: &str = "ec2-by-name";
+
+/// An active account in the organization.
+#[derive(Clone, Debug)]
+pub(crate) struct OrgAccount {
+    pub(crate) id: String,
+    pub(crate) name: String,
+}
+
+/// List the active accounts in the organization. This must be called with credentials from the management account or
+/// a delegated administrator.
+pub(crate) async fn list_accounts(sdk_config: &SdkConfig) -> Result<Vec<OrgAccount>> {
+    let organizations = aws_sdk_organizations::Client::new(sdk_config);
+    let mut stream = organizations.list_accounts().into_paginator().send();
+    let mut accounts = Vec::new();
+
+    while let Some(list_accounts_result) = stream.next().await {
+        let list_accounts_output = list_accounts_result.map_err(|e| Error::ListAccounts(Box::new(e)))?;
+        for account in list_accounts_output.accounts.unwrap_or_default() {
+            if account.status != Some(AccountStatus::Active) {
+                debug!("Skipping account {:?} with status {:?}", account.id, account.status);
+                continue;
+            }
+
+            if let Some(id) = account.id {
+                accounts.push(OrgAccount {
+                    id,
+                    name: account.name.unwrap_or_default(),
+                });
+            }
+        }
+    }
+
+    accounts.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(accounts)
+}
+
+/// The ARN of the role to assume in an account. `{account}` in the template is replaced by the account id; a template
+/// that is not an ARN is taken as a role name (or path and name) in the commercial partition.
+pub(crate) fn role_arn(template: &str, account_id: &str) -> String {
+    let role = template.replace("{account}", account_id);
+    if role.starts_with("arn:") {
+        role
+    } else {
+        format!("arn:aws:iam::{account_id}:role/{role}")
+    }
+}
+
+/// An SDK configuration that assumes the given role, using the credentials and settings of the base configuration.
+pub(crate) fn assume_role_config(sdk_config: &SdkConfig, role_arn: &str) -> Result<SdkConfig> {
+    let (Some(region), Some(base_provider)) = (sdk_config.region(), sdk_config.credentials_provider()) else {
+        return Err(Error::Runtime("A region and credentials are required to assume a role".to_string()));
+    };
+
+    let provider = AssumeRoleProvider::builder(role_arn)
+        .session_name(SESSION_NAME)
+        .region(region.clone())
+        .build(base_provider.clone());
+
+    let mut builder =
+        SdkConfig::builder().region(region.clone()).credentials_provider(SharedCredentialsProvider::new(provider));
+    builder.set_retry_config(sdk_config.retry_config().cloned());
+    builder.set_timeout_config(sdk_config.timeout_config().cloned());
+    builder.set_sleep_impl(sdk_config.sleep_impl());
+    builder.set_http_connector(sdk_config.http_connector().cloned());
+    Ok(builder.build())
+}
@@ -0,0 +1,117 @@
+use {
+    aws_sdk_pricing::model::{Filter as PricingFilter, FilterType},
+    aws_types::SdkConfig,
+    log::debug,
+    serde_json::Value,
+};
+
+/// Approximate hourly on-demand prices in USD for Linux instances with shared tenancy, keyed by region and then
+/// instance type.
+///
+/// This table is bundled so that cost estimates work offline. It was taken from the public price list in mid-2022 and
+/// is not updated automatically, so it will drift as prices change and will not cover newer instance types; use
+/// `--live-pricing` for current prices.
+const BUNDLED_PRICES: &[(&str, &[(&str, f64)])] =
+    &[("us-east-1", US_PRICES), ("us-east-2", US_PRICES), ("us-west-2", US_PRICES), ("eu-west-1", EU_WEST_1_PRICES)];
+
+const US_PRICES: &[(&str, f64)] = &[
+    ("c5.large", 0.085),
+    ("c5.xlarge", 0.17),
+    ("c5.2xlarge", 0.34),
+    ("c5.4xlarge", 0.68),
+    ("m5.large", 0.096),
+    ("m5.xlarge", 0.192),
+    ("m5.2xlarge", 0.384),
+    ("m5.4xlarge", 0.768),
+    ("r5.large", 0.126),
+    ("r5.xlarge", 0.252),
+    ("r5.2xlarge", 0.504),
+    ("t2.micro", 0.0116),
+    ("t2.small", 0.023),
+    ("t2.medium", 0.0464),
+    ("t2.large", 0.0928),
+    ("t3.nano", 0.0052),
+    ("t3.micro", 0.0104),
+    ("t3.small", 0.0208),
+    ("t3.medium", 0.0416),
+    ("t3.large", 0.0832),
+    ("t3.xlarge", 0.1664),
+    ("t3.2xlarge", 0.3328),
+];
+
+const EU_WEST_1_PRICES: &[(&str, f64)] = &[
+    ("c5.large", 0.096),
+    ("c5.xlarge", 0.192),
+    ("c5.2xlarge", 0.384),
+    ("c5.4xlarge", 0.768),
+    ("m5.large", 0.107),
+    ("m5.xlarge", 0.214),
+    ("m5.2xlarge", 0.428),
+    ("m5.4xlarge", 0.856),
+    ("r5.large", 0.141),
+    ("r5.xlarge", 0.282),
+    ("r5.2xlarge", 0.564),
+    ("t2.micro", 0.0126),
+    ("t2.small", 0.025),
+    ("t2.medium", 0.05),
+    ("t2.large", 0.1008),
+    ("t3.nano", 0.0057),
+    ("t3.micro", 0.0114),
+    ("t3.small", 0.0228),
+    ("t3.medium", 0.0456),
+    ("t3.large", 0.0912),
+    ("t3.xlarge", 0.1824),
+    ("t3.2xlarge", 0.3648),
+];
+
+/// The Price List API is only served from a few regions; us-east-1 carries prices for every region.
+const PRICING_REGION: &str = "us-east-1";
+
+/// Look up the approximate hourly price of an instance type in the bundled price table.
+pub(crate) fn bundled_hourly_price(region: &str, instance_type: &str) -> Option<f64> {
+    let (_, prices) = BUNDLED_PRICES.iter().find(|(r, _)| *r == region)?;
+    prices.iter().find(|(t, _)| *t == instance_type).map(|(_, price)| *price)
+}
+
+/// Look up the current hourly price of an instance type from the AWS Price List API.
+///
+/// Failures are logged and treated as a missing price, since cost estimates are informational only.
+pub(crate) async fn live_hourly_price(sdk_config: &SdkConfig, region: &str, instance_type: &str) -> Option<f64> {
+    let config = aws_sdk_pricing::config::Builder::from(sdk_config)
+        .region(aws_types::region::Region::new(PRICING_REGION))
+        .build();
+    let pricing = aws_sdk_pricing::Client::from_conf(config);
+
+    let mut request = pricing.get_products().service_code("AmazonEC2").max_results(1);
+    for (field, value) in [
+        ("regionCode", region),
+        ("instanceType", instance_type),
+        ("operatingSystem", "Linux"),
+        ("tenancy", "Shared"),
+        ("preInstalledSw", "NA"),
+        ("capacitystatus", "Used"),
+    ] {
+        request =
+            request.filters(PricingFilter::builder().r#type(FilterType::TermMatch).field(field).value(value).build());
+    }
+
+    let output = match request.send().await {
+        Ok(output) => output,
+        Err(e) => {
+            eprintln!("Warning: Failed to get the price of {instance_type} in {region}: {e}");
+            return None;
+        }
+    };
+
+    let product = output.price_list.unwrap_or_default().into_iter().next()?;
+    debug!("Received price list entry: {}", product);
+    on_demand_usd_price(&serde_json::from_str(&product).ok()?)
+}
+
+/// Extract the on-demand USD price from a Price List API product entry.
+fn on_demand_usd_price(product: &Value) -> Option<f64> {
+    let terms = product.get("terms")?.get("OnDemand")?.as_object()?;
+    let term = terms.values().next()?;
+    let dimension = term.get("priceDimensions")?.as_object()?.values().next()?;
+    dimension.get("pricePerUnit")?.get("USD")?.as_str()?.parse().ok()
+}
@@ -0,0 +1,35 @@
+use {
+    crate::error::{Error, Result},
+    std::{fmt::Display, str::FromStr},
+};
+
+/// Where progress messages, which say what an operation is about to do or has skipped, are written. Results (instance
+/// ids, state changes, JSON) always go to stdout.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) enum Progress {
+    #[default]
+    Stderr,
+    Stdout,
+}
+
+impl FromStr for Progress {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "stderr" => Ok(Self::Stderr),
+            "stdout" => Ok(Self::Stdout),
+            _ => Err(Error::InvalidUsage(format!("Unknown progress destination: {s}"))),
+        }
+    }
+}
+
+impl Progress {
+    /// Write a progress message followed by a newline.
+    pub(crate) fn println(self, message: impl Display) {
+        match self {
+            Self::Stderr => eprintln!("{message}"),
+            Self::Stdout => println!("{message}"),
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use {
+    crate::error::{Error, NResult, Result},
+    serde::Serialize,
+    std::{
+        fs,
+        io::{stderr, Write},
+        path::PathBuf,
+        str::FromStr,
+        sync::Mutex,
+    },
+};
+
+/// The format of the summary report emitted at the end of a run.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum ReportFormat {
+    Json,
+}
+
+impl FromStr for ReportFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "json" => Ok(Self::Json),
+            _ => Err(Error::InvalidUsage(format!("Unknown report format: {s}"))),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub(crate) struct Report {
+    pub(crate) results: Vec<NameReport>,
+}
+
+/// The outcome of resolving and operating on a single input name.
+#[derive(Debug, Serialize)]
+pub(crate) struct NameReport {
+    pub(crate) name: String,
+    pub(crate) instance_ids: Vec<String>,
+    pub(crate) status: Status,
+    pub(crate) error: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Status {
+    Ok,
+    Error,
+}
+
+/// Collects the per-name outcomes of every resolution in the run, so the report is written once at the end, to
+/// stderr or a file rather than mixed into the operation's output.
+#[derive(Debug)]
+pub(crate) struct Reporter {
+    format: ReportFormat,
+    path: Option<PathBuf>,
+    report: Mutex<Report>,
+}
+
+impl Reporter {
+    pub(crate) fn new(format: ReportFormat, path: Option<PathBuf>) -> Self {
+        Self {
+            format,
+            path,
+            report: Mutex::new(Report::default()),
+        }
+    }
+
+    /// Add the outcomes of one resolution to the report.
+    pub(crate) fn add(&self, report: Report) {
+        self.report.lock().unwrap().results.extend(report.results);
+    }
+
+    /// Write the report to the report file if one was given, otherwise to stderr.
+    pub(crate) fn write(&self) -> NResult {
+        let report = self.report.lock().unwrap();
+        let text = match self.format {
+            ReportFormat::Json => serde_json::to_string(&*report).expect("Failed to serialize report"),
+        };
+
+        match &self.path {
+            Some(path) => fs::write(path, format!("{text}\n")).map_err(|e| Error::Io(path.clone(), e)),
+            None => writeln!(stderr(), "{text}").map_err(|e| Error::Io("<stderr>".into(), e)),
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use {
+    crate::{
+        ec2::InstanceMap,
+        error::{Error, NResult, Result},
+    },
+    chrono::{DateTime, Utc},
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::BTreeMap,
+        fmt::{Display, Formatter, Result as FmtResult},
+        fs,
+        io::ErrorKind,
+        path::Path,
+        time::SystemTime,
+    },
+};
+
+/// The resolved instances and their states at a point in time, saved as JSON of the form
+/// `{"taken_at": "<rfc3339>", "instances": {"i-0abc": {"state": "running", "launch_time": "<rfc3339>"}}}`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub(crate) struct Snapshot {
+    pub(crate) taken_at: DateTime<Utc>,
+    pub(crate) instances: BTreeMap<String, InstanceSnapshot>,
+}
+
+/// The recorded state of a single instance.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub(crate) struct InstanceSnapshot {
+    pub(crate) state: String,
+    pub(crate) launch_time: Option<DateTime<Utc>>,
+}
+
+/// A difference between two snapshots.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum Change {
+    Added {
+        instance_id: String,
+        state: String,
+    },
+    Removed {
+        instance_id: String,
+        state: String,
+    },
+    StateChanged {
+        instance_id: String,
+        from: String,
+        to: String,
+    },
+}
+
+impl Snapshot {
+    /// Take a snapshot of the instances now.
+    pub(crate) fn of(instances: &InstanceMap) -> Self {
+        let instances = instances
+            .iter()
+            .map(|(instance_id, instance)| {
+                let state = instance.state.as_ref().and_then(|state| state.name.as_ref());
+                let launch_time = instance.launch_time.and_then(|t| SystemTime::try_from(t).ok()).map(DateTime::from);
+                let snapshot = InstanceSnapshot {
+                    state: state.map_or("unknown", |name| name.as_str()).to_string(),
+                    launch_time,
+                };
+                (instance_id.clone(), snapshot)
+            })
+            .collect();
+
+        Self {
+            taken_at: Utc::now(),
+            instances,
+        }
+    }
+
+    /// Read a snapshot file. A file that does not exist yet yields `None`.
+    pub(crate) fn read(path: &Path) -> Result<Option<Self>> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(Error::Io(path.into(), e)),
+        };
+
+        serde_json::from_str(&contents).map(Some).map_err(|e| Error::InvalidSnapshot(path.into(), e))
+    }
+
+    /// Write the snapshot, replacing the file.
+    pub(crate) fn write(&self, path: &Path) -> NResult {
+        let contents = serde_json::to_string_pretty(self).expect("Failed to serialize snapshot");
+        fs::write(path, contents).map_err(|e| Error::Io(path.into(), e))
+    }
+
+    /// The changes from this (earlier) snapshot to a later one, ordered by instance id.
+    pub(crate) fn diff(&self, later: &Snapshot) -> Vec<Change> {
+        let mut changes = Vec::new();
+        for (instance_id, instance) in &later.instances {
+            match self.instances.get(instance_id) {
+                None => changes.push(Change::Added {
+                    instance_id: instance_id.clone(),
+                    state: instance.state.clone(),
+                }),
+                Some(earlier) if earlier.state != instance.state => changes.push(Change::StateChanged {
+                    instance_id: instance_id.clone(),
+                    from: earlier.state.clone(),
+                    to: instance.state.clone(),
+                }),
+                Some(_) => (),
+            }
+        }
+
+        for (instance_id, instance) in &self.instances {
+            if !later.instances.contains_key(instance_id) {
+                changes.push(Change::Removed {
+                    instance_id: instance_id.clone(),
+                    state: instance.state.clone(),
+                });
+            }
+        }
+
+        changes.sort_by(|a, b| a.instance_id().cmp(b.instance_id()));
+        changes
+    }
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Added {
+                instance_id,
+                state,
+            } => write!(f, "+ {instance_id} {state}"),
+            Self::Removed {
+                instance_id,
+                state,
+            } => write!(f, "- {instance_id} {state}"),
+            Self::StateChanged {
+                instance_id,
+                from,
+                to,
+            } => write!(f, "~ {instance_id} {from} -> {to}"),
+        }
+    }
+}
+
+impl Change {
+    pub(crate) fn instance_id(&self) -> &str {
+        match self {
+            Self::Added {
+                instance_id,
+                ..
+            }
+            | Self::Removed {
+                instance_id,
+                ..
+            }
+            | Self::StateChanged {
+                instance_id,
+                ..
+            } => instance_id,
+        }
+    }
+}
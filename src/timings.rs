@@ -0,0 +1,61 @@
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    io::{stderr, Write},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Accumulated time spent in each phase of a run.
+///
+/// Lookups run concurrently, so the accumulated times may exceed the wall-clock time of the run.
+#[derive(Debug, Default)]
+pub(crate) struct Timings {
+    inner: Mutex<TimingsInner>,
+}
+
+#[derive(Debug, Default)]
+struct TimingsInner {
+    dns: Duration,
+    describe: BTreeMap<String, Duration>,
+    mutation: Duration,
+}
+
+impl Timings {
+    /// Await a DNS lookup, recording the time taken.
+    pub(crate) async fn time_dns<F: Future>(&self, f: F) -> F::Output {
+        let start = Instant::now();
+        let result = f.await;
+        self.inner.lock().unwrap().dns += start.elapsed();
+        result
+    }
+
+    /// Await an EC2 describe call, recording the time taken under the given filter category.
+    pub(crate) async fn time_describe<F: Future>(&self, category: &str, f: F) -> F::Output {
+        let start = Instant::now();
+        let result = f.await;
+        *self.inner.lock().unwrap().describe.entry(category.to_string()).or_default() += start.elapsed();
+        result
+    }
+
+    /// Await a mutating EC2 call, recording the time taken.
+    pub(crate) async fn time_mutation<F: Future>(&self, f: F) -> F::Output {
+        let start = Instant::now();
+        let result = f.await;
+        self.inner.lock().unwrap().mutation += start.elapsed();
+        result
+    }
+
+    /// Print the timing summary to stderr.
+    pub(crate) fn print(&self) {
+        let inner = self.inner.lock().unwrap();
+        let mut e = stderr();
+        writeln!(e, "Timings:").unwrap();
+        writeln!(e, "    DNS resolution: {:?}", inner.dns).unwrap();
+        writeln!(e, "    EC2 describe: {:?}", inner.describe.values().sum::<Duration>()).unwrap();
+        for (category, elapsed) in inner.describe.iter() {
+            writeln!(e, "        {category}: {elapsed:?}").unwrap();
+        }
+        writeln!(e, "    EC2 mutation: {:?}", inner.mutation).unwrap();
+    }
+}